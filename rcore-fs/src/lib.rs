@@ -0,0 +1,273 @@
+//! A read-only [rcore-fs] file system over a CPIO archive.
+//!
+//! [rcore-fs]: https://github.com/rcore-os/rcore-fs
+
+#![no_std]
+#![forbid(unsafe_code)]
+
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::sync::{Arc, Weak};
+use core::any::Any;
+
+use cpio::{CpioReader, CpioTree, FileType, HardLinks, ReadError};
+use rcore_fs::vfs::{self, FileSystem, FsError, FsInfo, INode, PollStatus, Result, Timespec};
+
+/// The block size reported in the metadata. Archives have no blocks, this
+/// only serves to compute the number of blocks of each file.
+const BLOCK_SIZE: usize = 512;
+
+/// The maximum length of file names, as on Linux.
+const NAME_MAX: usize = 255;
+
+/// A read-only file system serving the objects of an archive.
+///
+/// File data and symbolic link targets are served from the archive buffer
+/// without copying. The data of hard links is taken from the link which
+/// carries it, see [`HardLinks`]. Paths are looked up as in [`CpioTree`], so
+/// missing parent directories are synthesized.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use cpio_rcore_fs::CpioFs;
+/// use rcore_fs::vfs::FileSystem;
+///
+/// fn mount(initrd: &'static [u8]) -> Arc<dyn FileSystem> {
+///     CpioFs::new(initrd).expect("invalid initrd")
+/// }
+/// ```
+pub struct CpioFs {
+    tree: CpioTree<'static>,
+    links: HardLinks<'static>,
+    len: usize,
+    files: usize,
+    self_ref: Weak<CpioFs>,
+}
+
+impl CpioFs {
+    /// Creates a file system over the archive in the buffer.
+    pub fn new(buf: &'static [u8]) -> core::result::Result<Arc<Self>, ReadError> {
+        let tree = CpioTree::new(buf)?;
+        let links = HardLinks::new(buf)?;
+        let files = CpioReader::new(buf)?.count();
+        Ok(Arc::new_cyclic(|self_ref| CpioFs {
+            tree,
+            links,
+            len: buf.len(),
+            files,
+            self_ref: self_ref.clone(),
+        }))
+    }
+
+    fn inode(&self, path: &str) -> Arc<dyn INode> {
+        Arc::new(CpioINode {
+            fs: self.self_ref.upgrade().unwrap(),
+            path: path.into(),
+        })
+    }
+}
+
+impl FileSystem for CpioFs {
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn INode> {
+        self.inode("")
+    }
+
+    fn info(&self) -> FsInfo {
+        FsInfo {
+            bsize: BLOCK_SIZE,
+            frsize: BLOCK_SIZE,
+            blocks: self.len.div_ceil(BLOCK_SIZE),
+            bfree: 0,
+            bavail: 0,
+            files: self.files,
+            ffree: 0,
+            namemax: NAME_MAX,
+        }
+    }
+}
+
+/// An object of a [`CpioFs`].
+pub struct CpioINode {
+    fs: Arc<CpioFs>,
+    /// The normalized path.
+    path: String,
+}
+
+impl CpioINode {
+    fn cpio_metadata(&self) -> Result<cpio::Metadata> {
+        self.fs
+            .tree
+            .metadata(&self.path)
+            .ok_or(FsError::EntryNotFound)
+    }
+
+    /// Returns the data of a regular file, or the target of a symbolic link.
+    fn data(&self) -> Result<&'static [u8]> {
+        match self.fs.tree.get(&self.path) {
+            Some(obj) if obj.metadata.is_file() || obj.metadata.is_symlink() => {
+                Ok(self.fs.links.resolve(obj).data)
+            }
+            Some(obj) if obj.metadata.is_dir() => Err(FsError::IsDir),
+            Some(_) => Err(FsError::NotFile),
+            // Synthesized directory.
+            None => Err(FsError::IsDir),
+        }
+    }
+
+    fn check_dir(&self) -> Result<()> {
+        if self.cpio_metadata()?.is_dir() {
+            Ok(())
+        } else {
+            Err(FsError::NotDir)
+        }
+    }
+
+    fn parent(&self) -> &str {
+        self.path.rfind('/').map_or("", |i| &self.path[..i])
+    }
+}
+
+impl INode for CpioINode {
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        let data = self.data()?;
+        let start = offset.min(data.len());
+        let len = buf.len().min(data.len() - start);
+        buf[..len].copy_from_slice(&data[start..start + len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize> {
+        Err(FsError::NotSupported)
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        Ok(PollStatus {
+            read: true,
+            write: false,
+            error: false,
+        })
+    }
+
+    fn metadata(&self) -> Result<vfs::Metadata> {
+        let metadata = self.cpio_metadata()?;
+        let size = match metadata.file_type() {
+            Ok(FileType::Regular) | Ok(FileType::Symlink) => self.data()?.len(),
+            _ => 0,
+        };
+        convert_metadata(&metadata, size)
+    }
+
+    fn sync_all(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn sync_data(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn create(&self, _name: &str, _type: vfs::FileType, _mode: u32) -> Result<Arc<dyn INode>> {
+        Err(FsError::NotSupported)
+    }
+
+    fn create2(
+        &self,
+        _name: &str,
+        _type: vfs::FileType,
+        _mode: u32,
+        _data: usize,
+    ) -> Result<Arc<dyn INode>> {
+        Err(FsError::NotSupported)
+    }
+
+    fn find(&self, name: &str) -> Result<Arc<dyn INode>> {
+        self.check_dir()?;
+        match name {
+            "." => Ok(self.fs.inode(&self.path)),
+            ".." => Ok(self.fs.inode(self.parent())),
+            _ => {
+                let entry = self
+                    .fs
+                    .tree
+                    .read_dir(&self.path)
+                    .ok_or(FsError::NotDir)?
+                    .find(|entry| entry.name == name)
+                    .ok_or(FsError::EntryNotFound)?;
+                Ok(self.fs.inode(entry.path))
+            }
+        }
+    }
+
+    fn get_entry(&self, id: usize) -> Result<String> {
+        self.get_entry_with_metadata(id).map(|(_, name)| name)
+    }
+
+    /// Returns the entry `id` of the directory, where `.` and `..` come
+    /// first, followed by the children sorted by name.
+    fn get_entry_with_metadata(&self, id: usize) -> Result<(vfs::Metadata, String)> {
+        self.check_dir()?;
+        match id {
+            0 => Ok((self.metadata()?, ".".to_string())),
+            1 => Ok((self.fs.inode(self.parent()).metadata()?, "..".to_string())),
+            _ => {
+                let entry = self
+                    .fs
+                    .tree
+                    .read_dir(&self.path)
+                    .ok_or(FsError::NotDir)?
+                    .nth(id - 2)
+                    .ok_or(FsError::EntryNotFound)?;
+                let metadata = self.fs.inode(entry.path).metadata()?;
+                Ok((metadata, entry.name.to_string()))
+            }
+        }
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs.clone()
+    }
+
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn convert_metadata(metadata: &cpio::Metadata, size: usize) -> Result<vfs::Metadata> {
+    let type_ = match metadata.file_type() {
+        Ok(FileType::Regular) => vfs::FileType::File,
+        Ok(FileType::Directory) => vfs::FileType::Dir,
+        Ok(FileType::Symlink) => vfs::FileType::SymLink,
+        Ok(FileType::CharDevice) => vfs::FileType::CharDevice,
+        Ok(FileType::BlockDevice) => vfs::FileType::BlockDevice,
+        Ok(FileType::Fifo) => vfs::FileType::NamedPipe,
+        Ok(FileType::Socket) => vfs::FileType::Socket,
+        Err(_) => return Err(FsError::InvalidParam),
+    };
+    let mtime = Timespec {
+        sec: metadata.mtime.into(),
+        nsec: 0,
+    };
+    Ok(vfs::Metadata {
+        dev: metadata.dev() as usize,
+        inode: metadata.ino as usize,
+        size,
+        blk_size: BLOCK_SIZE,
+        blocks: size.div_ceil(BLOCK_SIZE),
+        atime: mtime,
+        mtime,
+        ctime: mtime,
+        type_,
+        mode: (metadata.mode & 0o7777) as u16,
+        nlinks: metadata.nlink as usize,
+        uid: metadata.uid as usize,
+        gid: metadata.gid as usize,
+        rdev: metadata.rdev() as usize,
+    })
+}
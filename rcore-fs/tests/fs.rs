@@ -0,0 +1,135 @@
+use cpio::{CpioNewcWriter, Metadata};
+use cpio_rcore_fs::CpioFs;
+use rcore_fs::vfs::{FileSystem, FileType, FsError};
+
+/// Writes an archive to a buffer which lives for the rest of the test run.
+fn archive() -> &'static [u8] {
+    let mut buf = vec![0u8; 4096];
+    let mut writer = CpioNewcWriter::new(&mut buf);
+    let file = Metadata {
+        ino: 5,
+        mode: 0o100644,
+        uid: 3,
+        nlink: 2,
+        mtime: 7,
+        ..Default::default()
+    };
+    let symlink = Metadata {
+        ino: 6,
+        mode: 0o120777,
+        nlink: 1,
+        ..Default::default()
+    };
+    let null = Metadata {
+        ino: 8,
+        mode: 0o20666,
+        nlink: 1,
+        rdev_major: 1,
+        rdev_minor: 3,
+        ..Default::default()
+    };
+    // As with GNU cpio, the data is stored on the last link only.
+    writer.write(&file, "etc/a", &[]).unwrap();
+    writer.write(&file, "etc/b", b"hello").unwrap();
+    writer.write(&symlink, "lnk", b"etc/a").unwrap();
+    writer.write(&null, "dev/null", &[]).unwrap();
+    let len = writer.finish().unwrap();
+    buf.truncate(len);
+    Box::leak(buf.into_boxed_slice())
+}
+
+#[test]
+fn read_files() {
+    let fs = CpioFs::new(archive()).unwrap();
+    let root = fs.root_inode();
+    let mut buf = [0u8; 16];
+
+    let a = root.lookup("etc/a").unwrap();
+    assert_eq!(a.read_at(1, &mut buf).unwrap(), 4);
+    assert_eq!(&buf[..4], b"ello");
+    assert_eq!(a.read_at(5, &mut buf).unwrap(), 0);
+    let metadata = a.metadata().unwrap();
+    assert_eq!(metadata.type_, FileType::File);
+    assert_eq!(metadata.size, 5);
+    assert_eq!(metadata.mode, 0o644);
+    assert_eq!(metadata.uid, 3);
+    assert_eq!(metadata.nlinks, 2);
+    assert_eq!(metadata.mtime.sec, 7);
+
+    let lnk = root.find("lnk").unwrap();
+    assert_eq!(lnk.metadata().unwrap().type_, FileType::SymLink);
+    assert_eq!(lnk.read_at(0, &mut buf).unwrap(), 5);
+    assert_eq!(&buf[..5], b"etc/a");
+
+    let null = root.lookup("dev/null").unwrap();
+    let metadata = null.metadata().unwrap();
+    assert_eq!(metadata.type_, FileType::CharDevice);
+    assert_eq!(metadata.rdev, 1 << 20 | 3);
+    assert_eq!(null.read_at(0, &mut buf).unwrap_err(), FsError::NotFile);
+
+    assert_eq!(fs.info().files, 4);
+}
+
+#[test]
+fn read_dirs() {
+    let fs = CpioFs::new(archive()).unwrap();
+    let root = fs.root_inode();
+
+    // `etc` is synthesized, as the archive has no object for it.
+    let etc = root.find("etc").unwrap();
+    assert_eq!(etc.metadata().unwrap().type_, FileType::Dir);
+    let names: Vec<_> = (0..).map_while(|id| etc.get_entry(id).ok()).collect();
+    assert_eq!(names, [".", "..", "a", "b"]);
+    let (metadata, name) = etc.get_entry_with_metadata(3).unwrap();
+    assert_eq!((metadata.size, name.as_str()), (5, "b"));
+    assert_eq!(
+        etc.find("..").unwrap().metadata().unwrap().type_,
+        FileType::Dir
+    );
+
+    assert!(matches!(root.find("missing"), Err(FsError::EntryNotFound)));
+    assert!(matches!(root.lookup("etc/a/b"), Err(FsError::NotDir)));
+    assert_eq!(etc.read_at(0, &mut [0; 4]).unwrap_err(), FsError::IsDir);
+}
+
+#[test]
+fn read_only() {
+    let fs = CpioFs::new(archive()).unwrap();
+    let root = fs.root_inode();
+    let a = root.lookup("etc/a").unwrap();
+    assert_eq!(a.write_at(0, b"x").unwrap_err(), FsError::NotSupported);
+    assert!(matches!(
+        root.create("x", FileType::File, 0o644),
+        Err(FsError::NotSupported)
+    ));
+}
+
+#[test]
+fn unnormalized_names() {
+    let mut buf = vec![0u8; 4096];
+    let mut writer = CpioNewcWriter::new(&mut buf);
+    let file = Metadata {
+        mode: 0o100644,
+        nlink: 1,
+        ..Default::default()
+    };
+    writer.write(&file, "etc//passwd", b"root").unwrap();
+    writer.write(&file, "usr/./bin/sh", b"sh").unwrap();
+    let len = writer.finish().unwrap();
+    buf.truncate(len);
+    let fs = CpioFs::new(Box::leak(buf.into_boxed_slice())).unwrap();
+    let root = fs.root_inode();
+
+    let names: Vec<_> = (0..).map_while(|id| root.get_entry(id).ok()).collect();
+    assert_eq!(names, [".", "..", "etc", "usr"]);
+    let usr = root.find("usr").unwrap();
+    let names: Vec<_> = (0..).map_while(|id| usr.get_entry(id).ok()).collect();
+    assert_eq!(names, [".", "..", "bin"]);
+
+    let mut data = [0u8; 8];
+    let passwd = root.lookup("etc/passwd").unwrap();
+    assert_eq!(passwd.read_at(0, &mut data).unwrap(), 4);
+    assert_eq!(&data[..4], b"root");
+    let sh = usr.find("bin").unwrap().find("sh").unwrap();
+    assert_eq!(sh.metadata().unwrap().size, 2);
+}
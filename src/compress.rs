@@ -0,0 +1,182 @@
+#[cfg(feature = "xz")]
+use alloc::boxed::Box;
+use std::io::{self, Write};
+
+#[cfg(feature = "xz")]
+use lzma_rust2::{LzmaOptions, LzmaWriter};
+
+use crate::{Compression, ReadError};
+
+#[cfg(feature = "gzip")]
+mod gzip;
+#[cfg(feature = "lz4")]
+mod lz4;
+#[cfg(feature = "xz")]
+mod xz;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+/// A writer which compresses the data written to it, producing initramfs
+/// images in a single step.
+///
+/// The output can be decompressed by the Linux kernel and the usual
+/// command line tools. [`finish`](Self::finish) must be called after the
+/// last write to complete the compressed stream.
+///
+/// # Levels
+///
+/// Levels are given on the scale of the format's command line tool, and
+/// clamped to its range:
+///
+/// - gzip: 0, which stores the data uncompressed, to 9. The default is 6.
+/// - Zstandard: 0 stores the data uncompressed, any other level uses the
+///   fastest mode of the encoder, which roughly matches `zstd -1`.
+/// - xz and LZMA: 0 to 9. The default is 6. xz streams use CRC32 checks,
+///   as the kernel's build scripts do.
+/// - LZ4: ignored, the fast mode is always used. The legacy format expected
+///   by the kernel is written, as by `lz4 -l`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+///
+/// use cpio::{Archiver, Compression, Compressor};
+///
+/// let file = BufWriter::new(File::create("initramfs.img").unwrap());
+/// let compressor = Compressor::with_level(file, Compression::Xz, 9).unwrap();
+/// Archiver::new("rootfs")
+///     .write(compressor)
+///     .unwrap()
+///     .finish()
+///     .unwrap();
+/// ```
+pub struct Compressor<W: Write> {
+    encoder: Encoder<W>,
+}
+
+/// The encoder of the selected format.
+enum Encoder<W: Write> {
+    #[cfg(feature = "gzip")]
+    Gzip(gzip::Encoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<W>),
+    #[cfg(feature = "xz")]
+    Xz(xz::Encoder<W>),
+    #[cfg(feature = "xz")]
+    Lzma(Box<LzmaWriter<W>>),
+    #[cfg(feature = "lz4")]
+    Lz4Legacy(lz4::Encoder<W>),
+}
+
+/// The default level of the formats which have levels.
+const DEFAULT_LEVEL: u32 = 6;
+
+impl<W: Write> Compressor<W> {
+    /// Creates a new compressor on the stream, with the default level of
+    /// the format.
+    ///
+    /// Returns [`ReadError::UnsupportedCompression`] if the feature of the
+    /// format is not enabled.
+    pub fn new(inner: W, compression: Compression) -> io::Result<Self> {
+        Self::with_level(inner, compression, DEFAULT_LEVEL)
+    }
+
+    /// Creates a new compressor on the stream, with the given level.
+    ///
+    /// Returns [`ReadError::UnsupportedCompression`] if the feature of the
+    /// format is not enabled.
+    #[cfg_attr(
+        not(any(feature = "gzip", feature = "zstd", feature = "xz")),
+        allow(unused_variables)
+    )]
+    pub fn with_level(inner: W, compression: Compression, level: u32) -> io::Result<Self> {
+        let encoder = match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Encoder::Gzip(gzip::Encoder::new(inner, level.min(9))?),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(inner, level)),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Encoder::Xz(xz::Encoder::new(inner, level.min(9))),
+            #[cfg(feature = "xz")]
+            Compression::Lzma => {
+                let options = LzmaOptions::with_preset(level.min(9));
+                Encoder::Lzma(Box::new(LzmaWriter::new_use_header(inner, &options, None)?))
+            }
+            #[cfg(feature = "lz4")]
+            Compression::Lz4Legacy => Encoder::Lz4Legacy(lz4::Encoder::new(inner)?),
+            #[allow(unreachable_patterns)]
+            _ => return Err(ReadError::UnsupportedCompression.into()),
+        };
+        Ok(Self { encoder })
+    }
+
+    /// Returns the compression format.
+    pub fn compression(&self) -> Compression {
+        match self.encoder {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(_) => Compression::Zstd,
+            #[cfg(feature = "xz")]
+            Encoder::Xz(_) => Compression::Xz,
+            #[cfg(feature = "xz")]
+            Encoder::Lzma(_) => Compression::Lzma,
+            #[cfg(feature = "lz4")]
+            Encoder::Lz4Legacy(_) => Compression::Lz4Legacy,
+        }
+    }
+
+    /// Completes the compressed stream and returns the underlying stream.
+    pub fn finish(self) -> io::Result<W> {
+        let mut inner = match self.encoder {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+            #[cfg(feature = "xz")]
+            Encoder::Xz(encoder) => encoder.finish()?,
+            #[cfg(feature = "xz")]
+            Encoder::Lzma(encoder) => encoder.finish()?,
+            #[cfg(feature = "lz4")]
+            Encoder::Lz4Legacy(encoder) => encoder.finish()?,
+        };
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(encoder) => encoder.write(buf),
+            #[cfg(feature = "xz")]
+            Encoder::Lzma(encoder) => encoder.write(buf),
+            #[cfg(feature = "lz4")]
+            Encoder::Lz4Legacy(encoder) => encoder.write(buf),
+        }
+    }
+
+    /// Flushes the underlying stream. Data buffered by the encoder is only
+    /// written by [`finish`](Self::finish).
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(encoder) => encoder.flush(),
+            #[cfg(feature = "xz")]
+            Encoder::Lzma(encoder) => encoder.inner_mut().flush(),
+            #[cfg(feature = "lz4")]
+            Encoder::Lz4Legacy(encoder) => encoder.flush(),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use alloc::boxed::Box;
+use std::io::{self, Write};
+
+use miniz_oxide::deflate::core::{create_comp_flags_from_zip_params, CompressorOxide};
+use miniz_oxide::deflate::stream::deflate;
+use miniz_oxide::{MZError, MZFlush, MZStatus};
+
+use crate::decompress::GZIP_MAGIC;
+
+const CM_DEFLATE: u8 = 8;
+/// The operating system field of the header, Unix as written by `gzip`.
+const OS_UNIX: u8 = 3;
+
+/// A streaming encoder of a gzip member.
+pub(super) struct Encoder<W> {
+    inner: W,
+    compressor: Box<CompressorOxide>,
+    crc: crc32fast::Hasher,
+    size: u32,
+    buf: Box<[u8]>,
+}
+
+impl<W: Write> Encoder<W> {
+    /// Writes the member header. As with `gzip -n`, no file name or
+    /// modification time is recorded.
+    pub fn new(mut inner: W, level: u32) -> io::Result<Self> {
+        let xfl = match level {
+            9 => 2,
+            1 => 4,
+            _ => 0,
+        };
+        let mut header = [0; 10];
+        header[..2].copy_from_slice(GZIP_MAGIC);
+        header[2] = CM_DEFLATE;
+        header[8] = xfl;
+        header[9] = OS_UNIX;
+        inner.write_all(&header)?;
+        // Negative window bits select a raw deflate stream.
+        let flags = create_comp_flags_from_zip_params(level as i32, -15, 0);
+        Ok(Self {
+            inner,
+            compressor: Box::new(CompressorOxide::new(flags)),
+            crc: crc32fast::Hasher::new(),
+            size: 0,
+            buf: vec![0; 0x8000].into_boxed_slice(),
+        })
+    }
+
+    /// Compresses the input, writing the compressed data to the stream.
+    /// Returns whether the stream ended, which it only does when finishing.
+    fn deflate(&mut self, mut input: &[u8], flush: MZFlush) -> io::Result<bool> {
+        loop {
+            let res = deflate(&mut self.compressor, input, &mut self.buf, flush);
+            input = &input[res.bytes_consumed..];
+            self.inner.write_all(&self.buf[..res.bytes_written])?;
+            match res.status {
+                Ok(MZStatus::StreamEnd) => return Ok(true),
+                // No progress can be made without more input.
+                Ok(_) | Err(MZError::Buf)
+                    if input.is_empty() && res.bytes_written < self.buf.len() =>
+                {
+                    return Ok(false)
+                }
+                Ok(_) => {}
+                Err(_) => return Err(io::ErrorKind::InvalidInput.into()),
+            }
+        }
+    }
+
+    /// Completes the member and returns the underlying stream.
+    pub fn finish(mut self) -> io::Result<W> {
+        while !self.deflate(&[], MZFlush::Finish)? {}
+        let mut trailer = [0; 8];
+        trailer[..4].copy_from_slice(&self.crc.clone().finalize().to_le_bytes());
+        trailer[4..].copy_from_slice(&self.size.to_le_bytes());
+        self.inner.write_all(&trailer)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.deflate(buf, MZFlush::None)?;
+        self.crc.update(buf);
+        self.size = self.size.wrapping_add(buf.len() as u32);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
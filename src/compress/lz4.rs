@@ -0,0 +1,63 @@
+use alloc::vec::Vec;
+use std::io::{self, Write};
+
+use lz4_flex::block::{compress_into, get_maximum_output_size};
+
+use crate::decompress::LZ4_LEGACY_MAGIC;
+
+/// The uncompressed length of all blocks but the last, as written by
+/// `lz4 -l`.
+const BLOCK_LEN: usize = 8 << 20;
+
+/// A streaming encoder of a legacy LZ4 stream.
+pub(super) struct Encoder<W> {
+    inner: W,
+    /// The uncompressed data of the current block.
+    block: Vec<u8>,
+    compressed: Vec<u8>,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(LZ4_LEGACY_MAGIC)?;
+        Ok(Self {
+            inner,
+            block: Vec::new(),
+            compressed: Vec::new(),
+        })
+    }
+
+    /// Compresses and writes the current block.
+    fn write_block(&mut self) -> io::Result<()> {
+        self.compressed
+            .resize(get_maximum_output_size(self.block.len()), 0);
+        let len = compress_into(&self.block, &mut self.compressed)
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        self.block.clear();
+        self.inner.write_all(&(len as u32).to_le_bytes())?;
+        self.inner.write_all(&self.compressed[..len])
+    }
+
+    /// Writes the last block and returns the underlying stream.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.block.is_empty() {
+            self.write_block()?;
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(BLOCK_LEN - self.block.len());
+        self.block.extend_from_slice(&buf[..len]);
+        if self.block.len() == BLOCK_LEN {
+            self.write_block()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
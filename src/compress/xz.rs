@@ -0,0 +1,80 @@
+use alloc::boxed::Box;
+use std::io::{self, Write};
+use std::mem;
+
+use lzma_rust2::{CheckType, XzOptions, XzWriter};
+
+/// An xz stream without blocks, with CRC32 checks, as written by
+/// `xz --check=crc32` for an empty input.
+const EMPTY_STREAM: [u8; 32] = [
+    0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00, 0x00, 0x01, 0x69, 0x22, 0xde, 0x36, 0x00, 0x00, 0x00, 0x00,
+    0x1c, 0xdf, 0x44, 0x21, 0x90, 0x42, 0x99, 0x0d, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x59, 0x5a,
+];
+
+/// A streaming encoder of an xz stream with CRC32 checks, as the kernel's
+/// build scripts use.
+pub(super) struct Encoder<W: Write> {
+    state: State<W>,
+    level: u32,
+}
+
+/// The underlying writer produces an invalid index for empty inputs, so it
+/// is only created once there is data.
+enum State<W: Write> {
+    Empty(W),
+    Writing(Box<XzWriter<W>>),
+    /// Creating the writer failed.
+    Failed,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W, level: u32) -> Self {
+        Self {
+            state: State::Empty(inner),
+            level,
+        }
+    }
+
+    /// Completes the stream and returns the underlying stream.
+    pub fn finish(self) -> io::Result<W> {
+        match self.state {
+            State::Empty(mut inner) => {
+                inner.write_all(&EMPTY_STREAM)?;
+                Ok(inner)
+            }
+            State::Writing(writer) => writer.finish(),
+            State::Failed => Err(failed()),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let State::Empty(_) = self.state {
+            if let State::Empty(inner) = mem::replace(&mut self.state, State::Failed) {
+                let mut options = XzOptions::with_preset(self.level);
+                options.set_check_sum_type(CheckType::Crc32);
+                self.state = State::Writing(Box::new(XzWriter::new(inner, options)?));
+            }
+        }
+        match &mut self.state {
+            State::Writing(writer) => writer.write(buf),
+            _ => Err(failed()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.state {
+            State::Empty(inner) => inner.flush(),
+            State::Writing(writer) => writer.inner_mut().flush(),
+            State::Failed => Err(failed()),
+        }
+    }
+}
+
+fn failed() -> io::Error {
+    io::Error::other("xz encoder failed previously")
+}
@@ -0,0 +1,66 @@
+use alloc::vec::Vec;
+use std::io::{self, Write};
+
+use ruzstd::encoding::{compress, CompressionLevel};
+
+/// The uncompressed length of each frame.
+///
+/// The encoder only compresses whole inputs, so the data is split into
+/// frames, which decoders read back to back.
+const FRAME_LEN: usize = 8 << 20;
+
+/// A streaming encoder of Zstandard frames.
+pub(super) struct Encoder<W> {
+    inner: W,
+    level: CompressionLevel,
+    /// The uncompressed data of the current frame.
+    frame: Vec<u8>,
+    compressed: Vec<u8>,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W, level: u32) -> Self {
+        let level = match level {
+            0 => CompressionLevel::Uncompressed,
+            _ => CompressionLevel::Fastest,
+        };
+        Self {
+            inner,
+            level,
+            frame: Vec::new(),
+            compressed: Vec::new(),
+        }
+    }
+
+    /// Compresses and writes the current frame.
+    fn write_frame(&mut self) -> io::Result<()> {
+        self.compressed.clear();
+        compress(&self.frame[..], &mut self.compressed, self.level);
+        self.frame.clear();
+        self.inner.write_all(&self.compressed)
+    }
+
+    /// Writes the last frame and returns the underlying stream.
+    pub fn finish(mut self) -> io::Result<W> {
+        // An empty input still needs a frame.
+        if !self.frame.is_empty() || self.compressed.is_empty() {
+            self.write_frame()?;
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(FRAME_LEN - self.frame.len());
+        self.frame.extend_from_slice(&buf[..len]);
+        if self.frame.len() == FRAME_LEN {
+            self.write_frame()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
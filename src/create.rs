@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::{CpioStreamWriter, Metadata};
+
+/// Creates newc archives from a directory on disk, like
+/// `find . | cpio -o -H newc`.
+///
+/// The directory itself is stored as `.`, followed by its contents with
+/// names relative to it. Directories are walked depth-first with entries
+/// sorted by name, so parents always come before their children and the
+/// order does not depend on the file system. Symbolic links are not
+/// followed.
+///
+/// Hard links are detected by device and inode number. As with GNU cpio,
+/// the data is stored on the last link only, the others are left empty.
+///
+/// Inode and link counts are truncated to 32 bits, and modification times
+/// are clamped to the range of an unsigned 32-bit timestamp.
+///
+/// # Reproducible builds
+///
+/// By default the metadata is recorded as found on disk, so archives of the
+/// same files differ between machines. Entries are always sorted, and the
+/// [`mtime`](Self::mtime),
+/// [`renumber_inodes`](Self::renumber_inodes),
+/// [`root_owned`](Self::root_owned) and [`zero_dev`](Self::zero_dev)
+/// options normalize the metadata which depends on the machine and the file
+/// system. With all of them enabled, trees with the same names, contents,
+/// modes, symbolic links and hard links produce identical bytes.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+///
+/// use cpio::{Archiver, Mtime};
+///
+/// let epoch = std::env::var("SOURCE_DATE_EPOCH").unwrap();
+/// let file = File::create("initramfs.cpio").unwrap();
+/// Archiver::new("rootfs")
+///     .mtime(Mtime::Clamp(epoch.parse().unwrap()))
+///     .renumber_inodes(true)
+///     .root_owned(true)
+///     .zero_dev(true)
+///     .write(BufWriter::new(file))
+///     .unwrap();
+/// ```
+pub struct Archiver {
+    src: PathBuf,
+    mtime: Mtime,
+    renumber_inodes: bool,
+    root_owned: bool,
+    zero_dev: bool,
+}
+
+/// The policy for recording modification times when creating archives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mtime {
+    /// Record modification times as found on disk. This is the default.
+    Preserve,
+    /// Record modification times later than the given timestamp as the
+    /// timestamp, like `tar --clamp-mtime`.
+    Clamp(u32),
+    /// Record the given timestamp for all files.
+    Set(u32),
+}
+
+/// A file found while walking the source directory.
+struct Entry {
+    name: String,
+    path: PathBuf,
+    metadata: fs::Metadata,
+}
+
+impl Archiver {
+    /// Creates a new archiver of the `src` directory.
+    pub fn new(src: impl Into<PathBuf>) -> Self {
+        Self {
+            src: src.into(),
+            mtime: Mtime::Preserve,
+            renumber_inodes: false,
+            root_owned: false,
+            zero_dev: false,
+        }
+    }
+
+    /// Sets the policy for recording modification times.
+    ///
+    /// For reproducible builds, clamp them to `SOURCE_DATE_EPOCH`.
+    pub fn mtime(mut self, mtime: Mtime) -> Self {
+        self.mtime = mtime;
+        self
+    }
+
+    /// Sets whether to number inodes sequentially from 1 in archive order,
+    /// instead of recording the inode numbers found on disk. Hard links
+    /// share a number. Disabled by default.
+    ///
+    /// Link counts are computed from the archived tree as well, since they
+    /// depend on the file system for directories, and on links outside the
+    /// tree for files: directories have 2 plus the number of their
+    /// subdirectories, files the number of their links in the archive.
+    pub fn renumber_inodes(mut self, renumber: bool) -> Self {
+        self.renumber_inodes = renumber;
+        self
+    }
+
+    /// Sets whether to record all files as owned by user and group 0.
+    /// Disabled by default.
+    pub fn root_owned(mut self, root_owned: bool) -> Self {
+        self.root_owned = root_owned;
+        self
+    }
+
+    /// Sets whether to zero the device numbers of the file system holding
+    /// each file. The device numbers of device nodes are kept. Disabled by
+    /// default.
+    ///
+    /// If the directory spans several file systems, enable
+    /// [`renumber_inodes`](Self::renumber_inodes) as well, or unrelated
+    /// files may be read back as hard links.
+    pub fn zero_dev(mut self, zero_dev: bool) -> Self {
+        self.zero_dev = zero_dev;
+        self
+    }
+
+    /// Writes the archive to the stream, returning the stream after the
+    /// trailer has been written.
+    pub fn write<W: Write>(&self, inner: W) -> io::Result<W> {
+        let mut entries = vec![Entry {
+            name: ".".into(),
+            path: self.src.clone(),
+            metadata: fs::metadata(&self.src)?,
+        }];
+        walk(&self.src, "", &mut entries)?;
+
+        // The index of the last entry of each hard link set, which carries
+        // the data, and the number of its entries.
+        let mut last_links = HashMap::new();
+        let mut link_counts = HashMap::new();
+        // The number of subdirectories of each directory.
+        let mut subdirs = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if let Some(key) = link_key(&entry.metadata) {
+                last_links.insert(key, i);
+                *link_counts.entry(key).or_insert(0) += 1;
+            }
+            if i > 0 && entry.metadata.is_dir() {
+                let parent = entry.name.rfind('/').map_or(".", |end| &entry.name[..end]);
+                *subdirs.entry(parent).or_insert(0) += 1;
+            }
+        }
+
+        let mut inodes = HashMap::new();
+        let mut writer = CpioStreamWriter::new(inner);
+        for (i, entry) in entries.iter().enumerate() {
+            let mut metadata = self.to_metadata(&entry.metadata, &mut inodes);
+            if self.renumber_inodes {
+                metadata.nlink = if entry.metadata.is_dir() {
+                    2 + subdirs.get(entry.name.as_str()).copied().unwrap_or(0)
+                } else {
+                    link_key(&entry.metadata).map_or(1, |key| link_counts[&key])
+                };
+            }
+            let carries_data = match link_key(&entry.metadata) {
+                Some(key) => last_links[&key] == i,
+                None => true,
+            };
+            let file_type = entry.metadata.file_type();
+            if file_type.is_file() && carries_data {
+                let file = File::open(&entry.path)?;
+                writer.write_from(&metadata, &entry.name, file, entry.metadata.len())?;
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(&entry.path)?;
+                writer.write(&metadata, &entry.name, target.as_os_str().as_bytes())?;
+            } else {
+                writer.write(&metadata, &entry.name, &[])?;
+            }
+        }
+        writer.finish()
+    }
+
+    /// Converts the metadata of a file on disk, applying the options.
+    ///
+    /// `inodes` maps the device and inode numbers of the files seen so far
+    /// to their new inode numbers.
+    fn to_metadata(
+        &self,
+        metadata: &fs::Metadata,
+        inodes: &mut HashMap<(u64, u64), u32>,
+    ) -> Metadata {
+        let ino = if self.renumber_inodes {
+            let next = inodes.len() as u32 + 1;
+            *inodes
+                .entry((metadata.dev(), metadata.ino()))
+                .or_insert(next)
+        } else {
+            metadata.ino() as u32
+        };
+        let mtime = u32::try_from(metadata.mtime().max(0)).unwrap_or(u32::MAX);
+        let mtime = match self.mtime {
+            Mtime::Preserve => mtime,
+            Mtime::Clamp(max) => mtime.min(max),
+            Mtime::Set(mtime) => mtime,
+        };
+        let (uid, gid) = if self.root_owned {
+            (0, 0)
+        } else {
+            (metadata.uid(), metadata.gid())
+        };
+        let (dev_major, dev_minor) = if self.zero_dev {
+            (0, 0)
+        } else {
+            split_dev(metadata.dev())
+        };
+        let (rdev_major, rdev_minor) = split_dev(metadata.rdev());
+        Metadata {
+            ino,
+            mode: metadata.mode(),
+            uid,
+            gid,
+            nlink: metadata.nlink() as u32,
+            mtime,
+            file_size: 0,
+            dev_major,
+            dev_minor,
+            rdev_major,
+            rdev_minor,
+            check: 0,
+        }
+    }
+}
+
+/// Appends the contents of the directory at `dir`, named `name` in the
+/// archive, to `entries`, recursing into subdirectories.
+fn walk(dir: &Path, name: &str, entries: &mut Vec<Entry>) -> io::Result<()> {
+    let mut file_names = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<io::Result<Vec<_>>>()?;
+    file_names.sort();
+    for file_name in file_names {
+        let path = dir.join(&file_name);
+        let file_name = file_name.into_string().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "file name is not valid UTF-8")
+        })?;
+        let name = match name {
+            "" => file_name,
+            _ => format!("{}/{}", name, file_name),
+        };
+        let metadata = fs::symlink_metadata(&path)?;
+        let is_dir = metadata.is_dir();
+        entries.push(Entry {
+            name: name.clone(),
+            path: path.clone(),
+            metadata,
+        });
+        if is_dir {
+            walk(&path, &name, entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the device and inode numbers of a file with several links.
+fn link_key(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    if metadata.nlink() > 1 && !metadata.is_dir() {
+        Some((metadata.dev(), metadata.ino()))
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn split_dev(dev: u64) -> (u32, u32) {
+    use nix::sys::stat::{major, minor};
+    (major(dev) as u32, minor(dev) as u32)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn split_dev(dev: u64) -> (u32, u32) {
+    // The encoding of device numbers is platform-specific, keep it whole.
+    ((dev >> 32) as u32, dev as u32)
+}
@@ -0,0 +1,265 @@
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::{Cursor, Format, ReadError};
+
+#[cfg(feature = "gzip")]
+mod gzip;
+#[cfg(feature = "lz4")]
+mod lz4;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "xz")]
+mod xz;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+#[cfg(feature = "std")]
+pub use stream::Decompressor;
+
+/// A compression format of initramfs images, detected by its magic number.
+///
+/// All formats are detected, but each is only decompressed if its cargo
+/// feature is enabled. Otherwise [`ReadError::UnsupportedCompression`] is
+/// returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip, as produced by `gzip` or `pigz`. Requires the `gzip` feature.
+    Gzip,
+    /// Zstandard, as produced by `zstd`. Requires the `zstd` feature.
+    Zstd,
+    /// xz, as produced by `xz`. Requires the `xz` feature.
+    Xz,
+    /// The legacy LZMA format, as produced by `xz --format=lzma`. Requires
+    /// the `xz` feature.
+    Lzma,
+    /// The legacy LZ4 format, as produced by `lz4 -l`. Requires the `lz4`
+    /// feature.
+    ///
+    /// Like the Linux kernel, only the legacy format is supported, not the
+    /// LZ4 frame format.
+    Lz4Legacy,
+}
+
+pub(crate) const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+pub(crate) const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+pub(crate) const XZ_MAGIC: &[u8] = &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
+pub(crate) const LZMA_MAGIC: &[u8] = &[0x5d, 0x00, 0x00];
+pub(crate) const LZ4_LEGACY_MAGIC: &[u8] = &[0x02, 0x21, 0x4c, 0x18];
+
+impl Compression {
+    /// Detects the compression format from the magic number at the start of
+    /// the buffer, returning `None` if it is not compressed.
+    pub fn detect(buf: &[u8]) -> Option<Self> {
+        let formats = [
+            (GZIP_MAGIC, Compression::Gzip),
+            (ZSTD_MAGIC, Compression::Zstd),
+            (XZ_MAGIC, Compression::Xz),
+            (LZMA_MAGIC, Compression::Lzma),
+            (LZ4_LEGACY_MAGIC, Compression::Lz4Legacy),
+        ];
+        formats
+            .iter()
+            .find(|(magic, _)| buf.starts_with(magic))
+            .map(|&(_, compression)| compression)
+    }
+
+    /// Decompresses the stream at the start of the buffer into `out` from
+    /// `pos` on, returning the number of bytes consumed and written.
+    fn decompress<O: Output + ?Sized>(
+        self,
+        buf: &[u8],
+        out: &mut O,
+        pos: usize,
+    ) -> Result<(usize, usize), ReadError> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => gzip::decompress(buf, out, pos),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::decompress(buf, out, pos),
+            #[cfg(feature = "xz")]
+            Compression::Xz => xz::decompress_xz(buf, out, pos),
+            #[cfg(feature = "xz")]
+            Compression::Lzma => xz::decompress_lzma(buf, out, pos),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4Legacy => lz4::decompress(buf, out, pos),
+            #[allow(unreachable_patterns)]
+            _ => Err(ReadError::UnsupportedCompression),
+        }
+    }
+}
+
+/// Decompresses the archive in the buffer into `out`, returning the
+/// decompressed archive.
+///
+/// Like the Linux initramfs loader, the buffer may hold several archives,
+/// each compressed or not, separated by NUL padding. This is the layout of
+/// images with an uncompressed early cpio, holding CPU microcode, followed
+/// by the compressed root file system. Uncompressed archives are copied and
+/// compressed ones are decompressed, dropping the padding, so the result
+/// should be read with `concatenated(true)`.
+///
+/// Buffers without compressed archives are returned unchanged, so the
+/// result can always be passed to a reader.
+///
+/// Returns [`ReadError::OutputTooShort`] if `out` cannot hold the
+/// decompressed archive. The gzip and LZ4 decompressors do not need an
+/// allocator, the gzip decompressor state, about 11 KiB, is kept on the
+/// stack.
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{decompress_into, CpioNewcWriter, CpioReader, Metadata};
+///
+/// fn find_init(image: &[u8], out: &mut [u8]) -> bool {
+///     let buf = decompress_into(image, out).unwrap();
+///     CpioReader::new(buf)
+///         .unwrap()
+///         .concatenated(true)
+///         .any(|obj| obj.unwrap().name == "init")
+/// }
+///
+/// let mut image = [0u8; 512];
+/// let mut writer = CpioNewcWriter::new(&mut image);
+/// writer.write(&Metadata::default(), "init", b"#!/bin/sh").unwrap();
+/// let len = writer.finish().unwrap();
+///
+/// let mut out = [0u8; 4096];
+/// assert!(find_init(&image[..len], &mut out));
+/// ```
+pub fn decompress_into<'a>(buf: &'a [u8], out: &'a mut [u8]) -> Result<&'a [u8], ReadError> {
+    if !is_compressed(buf) {
+        return Ok(buf);
+    }
+    let len = decompress_to(buf, out)?;
+    Ok(&out[..len])
+}
+
+/// Decompresses the archive in the buffer into a `Vec`.
+///
+/// Buffers without compressed archives are borrowed unchanged. See
+/// [`decompress_into`] for details.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cpio::{decompress, CpioReader};
+///
+/// let image = std::fs::read("initramfs.img").unwrap();
+/// let buf = decompress(&image).unwrap();
+/// for obj in CpioReader::new(&buf).unwrap().concatenated(true) {
+///     println!("{}", obj.unwrap().name);
+/// }
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decompress(buf: &[u8]) -> Result<Cow<'_, [u8]>, ReadError> {
+    if !is_compressed(buf) {
+        return Ok(Cow::Borrowed(buf));
+    }
+    let mut out = Vec::new();
+    let len = decompress_to(buf, &mut out)?;
+    out.truncate(len);
+    Ok(Cow::Owned(out))
+}
+
+/// Returns whether the buffer holds a compressed archive, possibly after
+/// uncompressed ones.
+fn is_compressed(mut buf: &[u8]) -> bool {
+    loop {
+        buf = skip_padding(buf);
+        if buf.is_empty() {
+            return false;
+        }
+        if Compression::detect(buf).is_some() {
+            return true;
+        }
+        match archive_len(buf) {
+            Ok(len) => buf = &buf[len..],
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Copies the uncompressed archives and decompresses the compressed ones in
+/// the buffer into `out`, returning the length of the output.
+fn decompress_to<O: Output + ?Sized>(mut buf: &[u8], out: &mut O) -> Result<usize, ReadError> {
+    let mut pos = 0;
+    loop {
+        buf = skip_padding(buf);
+        if buf.is_empty() {
+            return Ok(pos);
+        }
+        let consumed = match Compression::detect(buf) {
+            Some(compression) => {
+                let (consumed, written) = compression.decompress(buf, out, pos)?;
+                pos += written;
+                consumed
+            }
+            None => {
+                let len = archive_len(buf)?;
+                out.reserve(pos + len)?;
+                out.buf()[pos..pos + len].copy_from_slice(&buf[..len]);
+                pos += len;
+                len
+            }
+        };
+        buf = &buf[consumed..];
+    }
+}
+
+/// Returns the length of the uncompressed archive at the start of the
+/// buffer, up to and including the padding of its trailer.
+fn archive_len(buf: &[u8]) -> Result<usize, ReadError> {
+    let format = Format::detect(buf).ok_or(ReadError::InvalidMagic)?;
+    let mut cursor = Cursor::new(buf, format);
+    while let Some(obj) = cursor.next() {
+        obj?;
+    }
+    Ok(cursor.consumed())
+}
+
+/// Skips the NUL padding between archives.
+fn skip_padding(buf: &[u8]) -> &[u8] {
+    &buf[buf.iter().position(|&b| b != 0).unwrap_or(buf.len())..]
+}
+
+/// A decompression output buffer.
+trait Output {
+    fn buf(&mut self) -> &mut [u8];
+
+    /// Makes room for more output, or returns
+    /// [`ReadError::OutputTooShort`].
+    fn grow(&mut self) -> Result<(), ReadError>;
+
+    /// Makes room for at least `len` bytes of output.
+    fn reserve(&mut self, len: usize) -> Result<(), ReadError> {
+        while self.buf().len() < len {
+            self.grow()?;
+        }
+        Ok(())
+    }
+}
+
+impl Output for [u8] {
+    fn buf(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn grow(&mut self) -> Result<(), ReadError> {
+        Err(ReadError::OutputTooShort)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Output for Vec<u8> {
+    fn buf(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn grow(&mut self) -> Result<(), ReadError> {
+        let len = (self.len() * 2).max(0x10000);
+        self.resize(len, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+#[cfg(feature = "std")]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, Read};
+
+use miniz_oxide::inflate::core::{decompress as inflate, inflate_flags, DecompressorOxide};
+#[cfg(feature = "std")]
+use miniz_oxide::inflate::stream::{self, InflateState};
+use miniz_oxide::inflate::TINFLStatus;
+#[cfg(feature = "std")]
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+
+use super::{Output, GZIP_MAGIC};
+use crate::ReadError;
+
+const HEADER_LEN: usize = 10;
+const TRAILER_LEN: usize = 8;
+const CM_DEFLATE: u8 = 8;
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+const FRESERVED: u8 = 0xe0;
+
+/// Decompresses the gzip member at the start of the buffer into `out` from
+/// `pos` on, returning the number of bytes consumed and written.
+///
+/// Members following it are decompressed as separate streams by the caller.
+pub(super) fn decompress<O: Output + ?Sized>(
+    buf: &[u8],
+    out: &mut O,
+    mut pos: usize,
+) -> Result<(usize, usize), ReadError> {
+    let start = pos;
+    let mut input = &buf[header_len(buf)?..];
+    let mut inflater = DecompressorOxide::new();
+    loop {
+        let flags = inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+        let (status, consumed, written) = inflate(&mut inflater, input, out.buf(), pos, flags);
+        input = &input[consumed..];
+        pos += written;
+        match status {
+            TINFLStatus::Done => break,
+            TINFLStatus::HasMoreOutput => out.grow()?,
+            TINFLStatus::NeedsMoreInput | TINFLStatus::FailedCannotMakeProgress => {
+                return Err(ReadError::BufTooShort)
+            }
+            _ => return Err(ReadError::InvalidCompressedData),
+        }
+    }
+    let trailer = input.get(..TRAILER_LEN).ok_or(ReadError::BufTooShort)?;
+    check_trailer(trailer, &out.buf()[start..pos])?;
+    let consumed = buf.len() - input.len() + TRAILER_LEN;
+    Ok((consumed, pos - start))
+}
+
+/// Returns the length of the member header at the start of the buffer.
+///
+/// Returns [`ReadError::BufTooShort`] if the header is incomplete.
+fn header_len(buf: &[u8]) -> Result<usize, ReadError> {
+    if !GZIP_MAGIC.starts_with(&buf[..buf.len().min(GZIP_MAGIC.len())]) {
+        return Err(ReadError::InvalidCompressedData);
+    }
+    if buf.len() < HEADER_LEN {
+        return Err(ReadError::BufTooShort);
+    }
+    let flags = buf[3];
+    if buf[2] != CM_DEFLATE || flags & FRESERVED != 0 {
+        return Err(ReadError::InvalidCompressedData);
+    }
+    let mut len = HEADER_LEN;
+    if flags & FEXTRA != 0 {
+        let xlen = buf.get(len..len + 2).ok_or(ReadError::BufTooShort)?;
+        len += 2 + usize::from(u16::from_le_bytes([xlen[0], xlen[1]]));
+    }
+    for &flag in &[FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let rest = buf.get(len..).ok_or(ReadError::BufTooShort)?;
+            len += rest
+                .iter()
+                .position(|&b| b == 0)
+                .ok_or(ReadError::BufTooShort)?
+                + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        len += 2;
+    }
+    if len > buf.len() {
+        return Err(ReadError::BufTooShort);
+    }
+    Ok(len)
+}
+
+/// Checks the CRC-32 and length in the member trailer against the
+/// decompressed data.
+fn check_trailer(trailer: &[u8], data: &[u8]) -> Result<(), ReadError> {
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc != crc32fast::hash(data) || size != data.len() as u32 {
+        return Err(ReadError::ChecksumMismatch);
+    }
+    Ok(())
+}
+
+/// A streaming decoder of a gzip member.
+#[cfg(feature = "std")]
+pub(super) struct Decoder<R> {
+    inner: R,
+    state: Box<InflateState>,
+    crc: crc32fast::Hasher,
+    size: u32,
+    finished: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Decoder<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        read_header(&mut inner)?;
+        Ok(Self {
+            inner,
+            state: InflateState::new_boxed(DataFormat::Raw),
+            crc: crc32fast::Hasher::new(),
+            size: 0,
+            finished: false,
+        })
+    }
+
+    /// Returns the underlying stream, positioned after the member if it was
+    /// read to the end.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        let mut trailer = [0; TRAILER_LEN];
+        self.inner.read_exact(&mut trailer)?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc != self.crc.clone().finalize() || size != self.size {
+            return Err(ReadError::ChecksumMismatch.into());
+        }
+        self.finished = true;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !buf.is_empty() && !self.finished {
+            let input = self.inner.fill_buf()?;
+            let eof = input.is_empty();
+            let res = stream::inflate(&mut self.state, input, buf, MZFlush::None);
+            self.inner.consume(res.bytes_consumed);
+            let data = &buf[..res.bytes_written];
+            self.crc.update(data);
+            self.size = self.size.wrapping_add(data.len() as u32);
+            match res.status {
+                Ok(MZStatus::StreamEnd) => self.read_trailer()?,
+                Ok(_) | Err(MZError::Buf) if eof && data.is_empty() => {
+                    return Err(io::ErrorKind::UnexpectedEof.into())
+                }
+                Ok(_) | Err(MZError::Buf) => {}
+                Err(_) => return Err(ReadError::InvalidCompressedData.into()),
+            }
+            if !data.is_empty() {
+                return Ok(data.len());
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// Reads a member header from the stream.
+#[cfg(feature = "std")]
+fn read_header(inner: &mut impl Read) -> io::Result<()> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    loop {
+        match header_len(&header) {
+            Ok(_) => return Ok(()),
+            Err(ReadError::BufTooShort) => {
+                let mut byte = [0];
+                inner.read_exact(&mut byte)?;
+                header.push(byte[0]);
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
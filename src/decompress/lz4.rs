@@ -0,0 +1,155 @@
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use lz4_flex::block::{decompress_into, DecompressError};
+
+use super::{Output, LZ4_LEGACY_MAGIC};
+use crate::ReadError;
+
+/// The uncompressed length of all blocks but the last.
+const BLOCK_LEN: usize = 8 << 20;
+/// The largest compressed length of a block, `LZ4_COMPRESSBOUND(BLOCK_LEN)`.
+const MAX_BLOCK_LEN: usize = BLOCK_LEN + BLOCK_LEN / 255 + 16;
+
+/// The meaning of the 4 bytes preceding a block.
+enum Chunk {
+    /// The magic number of a concatenated stream, which is skipped.
+    Magic,
+    /// A block of the given compressed length.
+    Block(usize),
+    /// The end of the stream.
+    End,
+}
+
+/// Parses the 4 bytes preceding a block.
+///
+/// As in the Linux kernel, the stream ends at a zero length or when fewer
+/// than 4 bytes are left, since the format has no end marker.
+fn parse_chunk(buf: &[u8]) -> Result<Chunk, ReadError> {
+    if buf.len() < 4 {
+        return Ok(Chunk::End);
+    }
+    if buf[..4] == *LZ4_LEGACY_MAGIC {
+        return Ok(Chunk::Magic);
+    }
+    match u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize {
+        0 => Ok(Chunk::End),
+        len if len > MAX_BLOCK_LEN => Err(ReadError::InvalidCompressedData),
+        len => Ok(Chunk::Block(len)),
+    }
+}
+
+/// Decompresses the legacy LZ4 stream at the start of the buffer into `out`
+/// from `pos` on, returning the number of bytes consumed and written.
+pub(super) fn decompress<O: Output + ?Sized>(
+    buf: &[u8],
+    out: &mut O,
+    mut pos: usize,
+) -> Result<(usize, usize), ReadError> {
+    let start = pos;
+    let mut input = &buf[LZ4_LEGACY_MAGIC.len()..];
+    loop {
+        let len = match parse_chunk(input)? {
+            Chunk::Magic => 0,
+            Chunk::Block(len) => len,
+            Chunk::End => break,
+        };
+        let block = input.get(4..4 + len).ok_or(ReadError::BufTooShort)?;
+        while !block.is_empty() {
+            match decompress_into(block, &mut out.buf()[pos..]) {
+                Ok(written) => {
+                    pos += written;
+                    break;
+                }
+                Err(DecompressError::OutputTooSmall { .. }) => out.grow()?,
+                Err(_) => return Err(ReadError::InvalidCompressedData),
+            }
+        }
+        input = &input[4 + len..];
+    }
+    Ok((buf.len() - input.len(), pos - start))
+}
+
+/// A streaming decoder of a legacy LZ4 stream.
+#[cfg(feature = "std")]
+pub(super) struct Decoder<R> {
+    inner: R,
+    compressed: Vec<u8>,
+    block: Vec<u8>,
+    /// The position of the unread data in `block`.
+    pos: usize,
+    finished: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Decoder<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        inner.read_exact(&mut magic)?;
+        if magic != LZ4_LEGACY_MAGIC {
+            return Err(ReadError::InvalidCompressedData.into());
+        }
+        Ok(Self {
+            inner,
+            compressed: Vec::new(),
+            block: Vec::new(),
+            pos: 0,
+            finished: false,
+        })
+    }
+
+    /// Returns the underlying stream, positioned after the stream if it was
+    /// read to the end.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Decompresses the next block, setting `finished` at the end of the
+    /// stream.
+    fn next_block(&mut self) -> io::Result<()> {
+        let len = loop {
+            let mut buf = [0; 4];
+            let mut filled = 0;
+            while filled < buf.len() {
+                match self.inner.read(&mut buf[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            match parse_chunk(&buf[..filled])? {
+                Chunk::Magic => {}
+                Chunk::Block(len) => break len,
+                Chunk::End => {
+                    self.finished = true;
+                    return Ok(());
+                }
+            }
+        };
+        self.compressed.resize(len, 0);
+        self.inner.read_exact(&mut self.compressed)?;
+        self.block.resize(BLOCK_LEN, 0);
+        let len = decompress_into(&self.compressed, &mut self.block)
+            .map_err(|_| ReadError::InvalidCompressedData)?;
+        self.block.truncate(len);
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.block.len() && !self.finished {
+            self.next_block()?;
+        }
+        let data = &self.block[self.pos..];
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
@@ -0,0 +1,296 @@
+use alloc::boxed::Box;
+use std::io::{self, BufRead, Read};
+use std::mem;
+
+#[cfg(feature = "xz")]
+use lzma_rust2::{LzmaReader, XzReader};
+
+#[cfg(feature = "gzip")]
+use super::gzip;
+#[cfg(feature = "lz4")]
+use super::lz4;
+#[cfg(feature = "zstd")]
+use super::zstd;
+use super::Compression;
+use crate::{Format, ReadError};
+
+/// The length of the longest magic number, of both compression formats and
+/// archive formats.
+const MAGIC_LEN: usize = 6;
+
+/// A reader which transparently decompresses an initramfs image.
+///
+/// Like [`decompress_into`](crate::decompress_into), the stream may hold
+/// several archives, each compressed or not, separated by NUL padding.
+/// Uncompressed archives are passed through and compressed ones are
+/// decompressed, dropping the padding, so the output should be read with
+/// [`CpioStreamReader::concatenated`](crate::CpioStreamReader::concatenated).
+///
+/// The stream is buffered internally. Data which is neither compressed nor
+/// an archive is refused with [`ReadError::InvalidMagic`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::fs::File;
+///
+/// use cpio::{CpioStreamReader, Decompressor};
+///
+/// let file = File::open("initramfs.img").unwrap();
+/// let decompressor = Decompressor::new(file).unwrap();
+/// let mut reader = CpioStreamReader::new(decompressor).concatenated(true);
+/// while let Some(obj) = reader.next_object().unwrap() {
+///     println!("{}", obj.name);
+/// }
+/// ```
+pub struct Decompressor<R: Read> {
+    state: State<R>,
+}
+
+/// The archive being read.
+enum State<R: Read> {
+    /// An uncompressed archive, of which the current object has `remaining`
+    /// unread bytes.
+    Plain { inner: Peek<R>, remaining: u64 },
+    #[cfg(feature = "gzip")]
+    Gzip(gzip::Decoder<Peek<R>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Decoder<Peek<R>>),
+    #[cfg(feature = "xz")]
+    Xz(XzReader<Peek<R>>),
+    #[cfg(feature = "xz")]
+    Lzma(LzmaReader<Peek<R>>),
+    #[cfg(feature = "lz4")]
+    Lz4Legacy(lz4::Decoder<Peek<R>>),
+    /// A previous error left the stream in an unknown position.
+    Failed,
+}
+
+impl<R: Read> Decompressor<R> {
+    /// Creates a new decompressor, detecting the compression of the first
+    /// archive in the stream.
+    pub fn new(inner: R) -> io::Result<Self> {
+        let mut decompressor = Self {
+            state: State::Plain {
+                inner: Peek::new(inner),
+                remaining: 0,
+            },
+        };
+        decompressor.next()?;
+        Ok(decompressor)
+    }
+
+    /// Returns the compression of the archive being read, or `None` if it is
+    /// not compressed.
+    pub fn compression(&self) -> Option<Compression> {
+        match self.state {
+            State::Plain { .. } | State::Failed => None,
+            #[cfg(feature = "gzip")]
+            State::Gzip(_) => Some(Compression::Gzip),
+            #[cfg(feature = "zstd")]
+            State::Zstd(_) => Some(Compression::Zstd),
+            #[cfg(feature = "xz")]
+            State::Xz(_) => Some(Compression::Xz),
+            #[cfg(feature = "xz")]
+            State::Lzma(_) => Some(Compression::Lzma),
+            #[cfg(feature = "lz4")]
+            State::Lz4Legacy(_) => Some(Compression::Lz4Legacy),
+        }
+    }
+
+    /// Moves on to the next compressed archive, or the next object of an
+    /// uncompressed one, returning `false` at the end of the stream.
+    fn next(&mut self) -> io::Result<bool> {
+        let mut inner = match mem::replace(&mut self.state, State::Failed) {
+            State::Plain { inner, .. } => inner,
+            #[cfg(feature = "gzip")]
+            State::Gzip(decoder) => decoder.into_inner(),
+            #[cfg(feature = "zstd")]
+            State::Zstd(decoder) => decoder.into_inner(),
+            #[cfg(feature = "xz")]
+            State::Xz(decoder) => decoder.into_inner(),
+            #[cfg(feature = "xz")]
+            State::Lzma(decoder) => decoder.into_inner(),
+            #[cfg(feature = "lz4")]
+            State::Lz4Legacy(decoder) => decoder.into_inner(),
+            State::Failed => return Err(ReadError::InvalidCompressedData.into()),
+        };
+        if !inner.skip_padding()? {
+            self.state = State::Plain {
+                inner,
+                remaining: 0,
+            };
+            return Ok(false);
+        }
+        self.state = match Compression::detect(inner.peek(MAGIC_LEN)?) {
+            Some(compression) => decoder(compression, inner)?,
+            None => {
+                let remaining = object_len(&mut inner)?;
+                State::Plain { inner, remaining }
+            }
+        };
+        Ok(true)
+    }
+}
+
+/// Creates the decoder of a compressed archive.
+fn decoder<R: Read>(compression: Compression, inner: Peek<R>) -> io::Result<State<R>> {
+    match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(State::Gzip(gzip::Decoder::new(inner)?)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(State::Zstd(zstd::Decoder::new(inner)?)),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(State::Xz(XzReader::new(inner, false))),
+        #[cfg(feature = "xz")]
+        Compression::Lzma => Ok(State::Lzma(LzmaReader::new_mem_limit(
+            inner,
+            u32::MAX,
+            None,
+        )?)),
+        #[cfg(feature = "lz4")]
+        Compression::Lz4Legacy => Ok(State::Lz4Legacy(lz4::Decoder::new(inner)?)),
+        #[allow(unreachable_patterns)]
+        _ => Err(ReadError::UnsupportedCompression.into()),
+    }
+}
+
+/// Returns the length of the object of an uncompressed archive at the
+/// start of the stream, including its padding.
+fn object_len<R: Read>(inner: &mut Peek<R>) -> io::Result<u64> {
+    let format = Format::detect(inner.peek(MAGIC_LEN)?).ok_or(ReadError::InvalidMagic)?;
+    let header_len = format.header_len();
+    let header = inner.peek(header_len)?;
+    if header.len() < header_len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (metadata, name_size) = format.parse_header(&header[..header_len])?;
+    let name_len = header_len + name_size + format.name_padding(name_size);
+    let data_len = u64::from(metadata.file_size) + format.data_padding(metadata.file_size) as u64;
+    Ok(name_len as u64 + data_len)
+}
+
+impl<R: Read> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let res = match &mut self.state {
+                State::Plain { inner, remaining } if *remaining != 0 => {
+                    let len = (buf.len() as u64).min(*remaining) as usize;
+                    match inner.read(&mut buf[..len]) {
+                        Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+                        Ok(len) => {
+                            *remaining -= len as u64;
+                            Ok(len)
+                        }
+                        Err(err) => Err(err),
+                    }
+                }
+                State::Plain { .. } => Ok(0),
+                #[cfg(feature = "gzip")]
+                State::Gzip(decoder) => decoder.read(buf),
+                #[cfg(feature = "zstd")]
+                State::Zstd(decoder) => decoder.read(buf),
+                #[cfg(feature = "xz")]
+                State::Xz(decoder) => decoder.read(buf),
+                #[cfg(feature = "xz")]
+                State::Lzma(decoder) => decoder.read(buf),
+                #[cfg(feature = "lz4")]
+                State::Lz4Legacy(decoder) => decoder.read(buf),
+                State::Failed => Err(ReadError::InvalidCompressedData.into()),
+            };
+            match res {
+                Ok(0) => {}
+                Ok(len) => return Ok(len),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => return Err(err),
+                Err(err) => {
+                    self.state = State::Failed;
+                    return Err(err);
+                }
+            }
+            // The object or compressed archive ended.
+            if !self.next()? {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+/// The capacity of the buffer of [`Peek`].
+const PEEK_CAPACITY: usize = 0x2000;
+
+/// A buffered reader which can look ahead several bytes.
+struct Peek<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: Read> Peek<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; PEEK_CAPACITY].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Returns at least `len` buffered bytes, or all the bytes left at the
+    /// end of the stream.
+    fn peek(&mut self, len: usize) -> io::Result<&[u8]> {
+        if self.filled - self.pos < len {
+            self.buf.copy_within(self.pos..self.filled, 0);
+            self.filled -= self.pos;
+            self.pos = 0;
+            while self.filled < len {
+                match self.inner.read(&mut self.buf[self.filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => self.filled += n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    /// Skips NUL padding, returning `false` at the end of the stream.
+    fn skip_padding(&mut self) -> io::Result<bool> {
+        loop {
+            let buf = self.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(false);
+            }
+            let padding = buf.iter().take_while(|&&b| b == 0).count();
+            let more = padding < buf.len();
+            self.consume(padding);
+            if more {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl<R: Read> BufRead for Peek<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.peek(1)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<R: Read> Read for Peek<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.fill_buf()?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(not(feature = "std"))]
+use lzma_rust2::Read;
+use lzma_rust2::{LzmaReader, XzReader};
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use super::Output;
+use crate::ReadError;
+
+/// Decompresses the xz stream at the start of the buffer into `out` from
+/// `pos` on, returning the number of bytes consumed and written.
+pub(super) fn decompress_xz<O: Output + ?Sized>(
+    buf: &[u8],
+    out: &mut O,
+    pos: usize,
+) -> Result<(usize, usize), ReadError> {
+    let mut input = buf;
+    let written = read_to_end(XzReader::new(&mut input, false), out, pos)?;
+    Ok((buf.len() - input.len(), written))
+}
+
+/// Decompresses the LZMA stream at the start of the buffer into `out` from
+/// `pos` on, returning the number of bytes consumed and written.
+pub(super) fn decompress_lzma<O: Output + ?Sized>(
+    buf: &[u8],
+    out: &mut O,
+    pos: usize,
+) -> Result<(usize, usize), ReadError> {
+    let mut input = buf;
+    let reader = LzmaReader::new_mem_limit(&mut input, u32::MAX, None)
+        .map_err(|_| ReadError::InvalidCompressedData)?;
+    let written = read_to_end(reader, out, pos)?;
+    Ok((buf.len() - input.len(), written))
+}
+
+/// Reads the decompressed data into `out` from `pos` on, returning its
+/// length.
+fn read_to_end<O: Output + ?Sized>(
+    mut reader: impl Read,
+    out: &mut O,
+    mut pos: usize,
+) -> Result<usize, ReadError> {
+    let start = pos;
+    loop {
+        let res = if pos < out.buf().len() {
+            reader.read(&mut out.buf()[pos..])
+        } else {
+            // Only grow the output if there is more data, so that an output
+            // of the exact size suffices.
+            let mut byte = [0];
+            let res = reader.read(&mut byte);
+            if let Ok(1) = res {
+                out.grow()?;
+                out.buf()[pos] = byte[0];
+            }
+            res
+        };
+        match res {
+            Ok(0) => return Ok(pos - start),
+            Ok(len) => pos += len,
+            Err(_) => return Err(ReadError::InvalidCompressedData),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use std::io;
+
+use ruzstd::decoding::{BlockDecodingStrategy, FrameDecoder};
+use ruzstd::io::Read;
+
+use super::Output;
+use crate::ReadError;
+
+/// The number of bytes decoded at a time.
+const CHUNK_LEN: usize = 0x20000;
+
+/// Decompresses the Zstandard frame at the start of the buffer into `out`
+/// from `pos` on, returning the number of bytes consumed and written.
+pub(super) fn decompress<O: Output + ?Sized>(
+    buf: &[u8],
+    out: &mut O,
+    mut pos: usize,
+) -> Result<(usize, usize), ReadError> {
+    let start = pos;
+    let mut input = buf;
+    let mut decoder = FrameDecoder::new();
+    decoder
+        .init(&mut input)
+        .map_err(|_| ReadError::InvalidCompressedData)?;
+    while !decoder.is_finished() || decoder.can_collect() != 0 {
+        decoder
+            .decode_blocks(&mut input, BlockDecodingStrategy::UptoBytes(CHUNK_LEN))
+            .map_err(|_| ReadError::InvalidCompressedData)?;
+        while decoder.can_collect() != 0 {
+            if pos == out.buf().len() {
+                out.grow()?;
+            }
+            pos += decoder
+                .read(&mut out.buf()[pos..])
+                .map_err(|_| ReadError::InvalidCompressedData)?;
+        }
+    }
+    check_checksum(&decoder)?;
+    Ok((buf.len() - input.len(), pos - start))
+}
+
+/// Checks the optional checksum at the end of the frame against the
+/// decompressed data.
+fn check_checksum(decoder: &FrameDecoder) -> Result<(), ReadError> {
+    match (
+        decoder.get_checksum_from_data(),
+        decoder.get_calculated_checksum(),
+    ) {
+        (Some(expected), Some(actual)) if expected != actual => Err(ReadError::ChecksumMismatch),
+        _ => Ok(()),
+    }
+}
+
+/// A streaming decoder of a Zstandard frame.
+#[cfg(feature = "std")]
+pub(super) struct Decoder<R> {
+    inner: R,
+    decoder: Box<FrameDecoder>,
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> Decoder<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut decoder = Box::new(FrameDecoder::new());
+        decoder
+            .init(&mut inner)
+            .map_err(|_| ReadError::InvalidCompressedData)?;
+        Ok(Self { inner, decoder })
+    }
+
+    /// Returns the underlying stream, positioned after the frame if it was
+    /// read to the end.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> io::Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while self.decoder.can_collect() == 0 && !self.decoder.is_finished() {
+            let strategy = BlockDecodingStrategy::UptoBytes(buf.len().max(CHUNK_LEN));
+            self.decoder
+                .decode_blocks(&mut self.inner, strategy)
+                .map_err(|_| ReadError::InvalidCompressedData)?;
+        }
+        let len = self.decoder.read(buf)?;
+        if len == 0 {
+            check_checksum(&self.decoder)?;
+        }
+        Ok(len)
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{inner, pad_to_4, Format, Object};
+
+/// A file found by [`find_cpio_data`].
+#[derive(Debug, Clone, Copy)]
+pub struct CpioData<'a> {
+    /// The object of the file. Its name is the full pathname, including the
+    /// searched prefix.
+    pub object: Object<'a>,
+    /// The offset of the object's header in the buffer.
+    pub offset: usize,
+    /// The offset of the file data in the buffer.
+    pub data_offset: usize,
+    /// The offset right after the object, where the search continues. This
+    /// is the `nextoff` of the Linux kernel.
+    pub next_offset: usize,
+}
+
+/// Searches the buffer for regular files whose names start with `path`,
+/// without allocating, like `find_cpio_data` in the Linux kernel.
+///
+/// This is how the kernel finds CPU microcode, such as
+/// `kernel/x86/microcode/GenuineIntel.bin`, in the uncompressed early cpio
+/// at the start of an initramfs image, before any allocator exists.
+///
+/// As in the kernel, only the newc and crc formats are recognized and
+/// checksums are not verified. Headers are 4-byte aligned, NUL padding
+/// between archives is skipped, and trailers do not end the search, so the
+/// buffer may hold several concatenated archives. Unlike the kernel, bytes
+/// before the first valid object are skipped, in steps of 4 bytes, even if
+/// they contain a magic number. The search ends at the end of the buffer,
+/// or at the first bytes which are not a valid object after an archive was
+/// found, such as a compressed archive.
+///
+/// Names are compared as stored, so `./kernel/x86/microcode/` has to be
+/// searched for if the archive was created from `find .` output.
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{find_cpio_data, CpioNewcWriter, Metadata};
+///
+/// let file = Metadata {
+///     mode: 0o100644,
+///     nlink: 1,
+///     ..Default::default()
+/// };
+/// let mut buf = [0u8; 1024];
+/// let mut writer = CpioNewcWriter::new(&mut buf[8..]);
+/// writer.write(&file, "kernel/x86/microcode/GenuineIntel.bin", b"ucode").unwrap();
+/// writer.write(&file, "init", b"#!/bin/sh").unwrap();
+/// writer.finish().unwrap();
+///
+/// let found = find_cpio_data("kernel/x86/microcode/", &buf).next().unwrap();
+/// assert_eq!(found.object.name, "kernel/x86/microcode/GenuineIntel.bin");
+/// assert_eq!(found.offset, 8);
+/// assert_eq!(&buf[found.data_offset..][..5], b"ucode");
+/// ```
+pub fn find_cpio_data<'a, 'p>(path: &'p str, buf: &'a [u8]) -> FindCpioData<'a, 'p> {
+    FindCpioData {
+        buf,
+        path,
+        pos: 0,
+        in_archive: false,
+    }
+}
+
+/// An iterator over the files found by [`find_cpio_data`].
+pub struct FindCpioData<'a, 'p> {
+    buf: &'a [u8],
+    path: &'p str,
+    /// The offset of the next header.
+    pos: usize,
+    /// Whether an archive was found, after which other bytes end the search.
+    in_archive: bool,
+}
+
+impl<'a> Iterator for FindCpioData<'a, '_> {
+    type Item = CpioData<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = &self.buf[self.pos..];
+            if rest.len() < 4 {
+                self.pos = self.buf.len();
+                return None;
+            }
+            if rest[..4] == [0; 4] {
+                self.pos += 4;
+                continue;
+            }
+            let newc = matches!(Format::detect(rest), Some(Format::Newc | Format::Crc));
+            if !newc && !self.in_archive {
+                self.pos += 4;
+                continue;
+            }
+            let mut cursor = rest;
+            let object = match inner(&mut cursor, Format::Newc, false) {
+                Ok(object) => object,
+                Err(_) if !self.in_archive => {
+                    // A magic number in other data, keep searching.
+                    self.pos += 4;
+                    continue;
+                }
+                Err(_) => {
+                    self.pos = self.buf.len();
+                    return None;
+                }
+            };
+            self.in_archive = true;
+            let offset = self.pos;
+            self.pos = self.buf.len() - cursor.len();
+            if object.metadata.is_file() && object.name.starts_with(self.path) {
+                let data_len = object.data.len();
+                return Some(CpioData {
+                    object,
+                    offset,
+                    data_offset: self.pos - pad_to_4(data_len) - data_len,
+                    next_offset: self.pos,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, OpenOptions, Permissions};
+use std::io::{self, Write};
+use std::os::unix::fs::{lchown, symlink, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use nix::fcntl::OFlag;
+use nix::sys::stat::{utimensat, Mode, SFlag, UtimensatFlags};
+use nix::sys::time::TimeSpec;
+
+use crate::index::normalize;
+use crate::{FileType, Metadata, Object, ReadError};
+
+/// Extracts archives to a directory on disk, like `cpio -idm`.
+///
+/// Regular files, directories, symbolic links, hard links, FIFOs, sockets
+/// and device nodes are created. Device nodes are skipped if the process is
+/// not privileged to create them. Ownership, mode and modification time are
+/// applied in that order, to directories after all of their contents.
+///
+/// CPIO archives only record numeric user and group IDs, so ownership is
+/// always restored numerically, as with `cpio --numeric-uid-gid`.
+///
+/// Objects never escape the destination directory: leading `/` are
+/// stripped from absolute paths, and unless
+/// [`allow_unsafe_paths`](Self::allow_unsafe_paths) is set, paths containing
+/// `..` or leading through a symbolic link are refused with an
+/// [`UnsafePathError`]. Files are never written through symbolic links, and
+/// hard links are only made to objects of the same file type. Paths of
+/// earlier objects are checked again before they are linked to or their
+/// metadata is applied, as later objects may have replaced their parent
+/// directories. These checks assume that the destination is not modified
+/// concurrently by other processes.
+///
+/// Existing objects are only removed once their replacement was created
+/// next to them, and are kept if it cannot be created.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cpio::{CpioReader, Extractor, Overwrite};
+///
+/// let buf = std::fs::read("initramfs.cpio").unwrap();
+/// Extractor::new("rootfs")
+///     .overwrite(Overwrite::Always)
+///     .preserve_ownership(true)
+///     .extract(CpioReader::new(&buf).unwrap())
+///     .unwrap();
+/// ```
+pub struct Extractor {
+    dest: PathBuf,
+    overwrite: Overwrite,
+    preserve_ownership: bool,
+    preserve_mtime: bool,
+    allow_unsafe_paths: bool,
+}
+
+/// The policy for replacing existing files during extraction.
+///
+/// Existing directories are never replaced by directories, only their
+/// metadata is updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Keep existing files.
+    Never,
+    /// Replace existing files older than the archived ones. This is the
+    /// default, as in `cpio -i`.
+    IfNewer,
+    /// Always replace existing files, as in `cpio -iu`.
+    Always,
+}
+
+impl Extractor {
+    /// Creates a new extractor into the `dest` directory.
+    pub fn new(dest: impl Into<PathBuf>) -> Self {
+        Self {
+            dest: dest.into(),
+            overwrite: Overwrite::IfNewer,
+            preserve_ownership: false,
+            preserve_mtime: true,
+            allow_unsafe_paths: false,
+        }
+    }
+
+    /// Sets the policy for replacing existing files.
+    pub fn overwrite(mut self, overwrite: Overwrite) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Sets whether to restore the archived owner and group, which usually
+    /// requires privileges. Disabled by default.
+    pub fn preserve_ownership(mut self, preserve: bool) -> Self {
+        self.preserve_ownership = preserve;
+        self
+    }
+
+    /// Sets whether to restore the archived modification times. Enabled by
+    /// default.
+    pub fn preserve_mtime(mut self, preserve: bool) -> Self {
+        self.preserve_mtime = preserve;
+        self
+    }
+
+    /// Sets whether to allow paths containing `..` or leading through
+    /// symbolic links. Only enable this for trusted archives.
+    pub fn allow_unsafe_paths(mut self, allow: bool) -> Self {
+        self.allow_unsafe_paths = allow;
+        self
+    }
+
+    /// Extracts the objects yielded by a reader.
+    pub fn extract<'a, I>(&self, objects: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = Result<Object<'a>, ReadError>>,
+    {
+        // The first extracted object of each hard link set.
+        let mut links = HashMap::new();
+        let mut dirs = Vec::new();
+        fs::create_dir_all(&self.dest)?;
+        for obj in objects {
+            let obj = obj?;
+            let metadata = &obj.metadata;
+            let name = normalize(obj.name);
+            if name.is_empty() {
+                dirs.push((name, *metadata));
+                continue;
+            }
+            if !self.allow_unsafe_paths {
+                self.check_path(obj.name, &name)?;
+            }
+            let path = self.dest.join(&*name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file_type = metadata.file_type()?;
+            let existing = match self.prepare(&path, metadata, file_type)? {
+                Some(existing) => existing,
+                None => continue,
+            };
+
+            let is_link = file_type != FileType::Directory && metadata.nlink > 1;
+            let key = (metadata.dev_major, metadata.dev_minor, metadata.ino);
+            if let Some(first) = links.get_mut(&key).filter(|_| is_link) {
+                let first_path = self.check_link(obj.name, first, file_type)?;
+                // GNU cpio stores the data on the last link only, other
+                // archivers on every link. It is written to the shared inode
+                // once, through the first link.
+                let write =
+                    file_type == FileType::Regular && !first.has_data && !obj.data.is_empty();
+                if write {
+                    write_file(&first_path, obj.data, false)?;
+                    first.has_data = true;
+                }
+                if first.name != name {
+                    self.replace(&path, existing, file_type, |path| {
+                        fs::hard_link(&first_path, path)?;
+                        Ok(true)
+                    })?;
+                }
+                if write {
+                    self.apply_metadata(&path, metadata)?;
+                }
+                continue;
+            }
+
+            // Existing directories are kept, only their metadata is updated.
+            if file_type == FileType::Directory && existing == Existing::Dir {
+                dirs.push((name, *metadata));
+                continue;
+            }
+            let created = self.replace(&path, existing, file_type, |path| {
+                match file_type {
+                    FileType::Regular => write_file(path, obj.data, true)?,
+                    FileType::Directory => fs::create_dir(path)?,
+                    FileType::Symlink => {
+                        let target = obj.symlink_target()?.unwrap_or_default();
+                        symlink(target, path)?;
+                    }
+                    FileType::CharDevice | FileType::BlockDevice => {
+                        match mknod(path, file_type, metadata) {
+                            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                                return Ok(false)
+                            }
+                            res => res?,
+                        }
+                    }
+                    FileType::Fifo | FileType::Socket => mknod(path, file_type, metadata)?,
+                }
+                Ok(true)
+            })?;
+            // Device nodes are skipped if they cannot be created.
+            if !created {
+                continue;
+            }
+            if file_type == FileType::Directory {
+                dirs.push((name, *metadata));
+                continue;
+            }
+            self.apply_metadata(&path, metadata)?;
+            // Only recorded once created, so that the path is known to hold
+            // an object of the file type.
+            if is_link {
+                let link = Link {
+                    name,
+                    file_type,
+                    has_data: !obj.data.is_empty(),
+                };
+                links.insert(key, link);
+            }
+        }
+
+        // Apply directory metadata last, so that restrictive modes and
+        // modification times are not affected by their contents. Directories
+        // replaced by later objects, or below a replaced parent directory,
+        // are skipped, so that the metadata is never applied through a
+        // symbolic link.
+        for (name, metadata) in dirs.iter().rev() {
+            if name.is_empty() {
+                self.apply_metadata(&self.dest, metadata)?;
+                continue;
+            }
+            if !self.allow_unsafe_paths && self.check_path(name, name).is_err() {
+                continue;
+            }
+            let path = self.dest.join(&**name);
+            if fs::symlink_metadata(&path).is_ok_and(|existing| existing.is_dir()) {
+                self.apply_metadata(&path, metadata)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the object named `name`, normalized to `path`, does not
+    /// escape the destination directory.
+    fn check_path(&self, name: &str, path: &str) -> Result<(), UnsafePathError> {
+        let error = |reason| UnsafePathError {
+            name: name.into(),
+            reason,
+        };
+        if path.split('/').any(|component| component == "..") {
+            return Err(error(UnsafePathReason::ParentDir));
+        }
+        let parent = path.rfind('/').map_or("", |i| &path[..i]);
+        let mut ancestor = self.dest.clone();
+        for component in parent.split('/').filter(|c| !c.is_empty()) {
+            ancestor.push(component);
+            match fs::symlink_metadata(&ancestor) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Err(error(UnsafePathReason::Symlink));
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that an object named `name` of `file_type` may be linked to
+    /// the first extracted object of its hard link set, returning the path
+    /// of the latter.
+    ///
+    /// The first object, or one of its parent directories, may have been
+    /// replaced by a later object, possibly a symbolic link leading outside
+    /// the destination, so its path is checked again, and its type on disk.
+    fn check_link(&self, name: &str, first: &Link<'_>, file_type: FileType) -> io::Result<PathBuf> {
+        if !self.allow_unsafe_paths {
+            self.check_path(name, &first.name)?;
+        }
+        let path = self.dest.join(&*first.name);
+        let existing = fs::symlink_metadata(&path)?;
+        if first.file_type != file_type || FileType::from_mode(existing.mode()) != Some(file_type) {
+            let err = UnsafePathError {
+                name: name.into(),
+                reason: UnsafePathReason::HardLink,
+            };
+            return Err(err.into());
+        }
+        Ok(path)
+    }
+
+    /// Looks up the object existing at the `path` of a new object, returning
+    /// `None` if the new object should be skipped according to the
+    /// overwrite policy.
+    fn prepare(
+        &self,
+        path: &Path,
+        metadata: &Metadata,
+        file_type: FileType,
+    ) -> io::Result<Option<Existing>> {
+        let existing = match fs::symlink_metadata(path) {
+            Ok(existing) => existing,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Some(Existing::Vacant)),
+            Err(err) => return Err(err),
+        };
+        if file_type == FileType::Directory && existing.is_dir() {
+            return Ok(Some(Existing::Dir));
+        }
+        let replace = match self.overwrite {
+            Overwrite::Never => false,
+            Overwrite::IfNewer => existing.mtime() < i64::from(metadata.mtime),
+            Overwrite::Always => true,
+        };
+        if !replace {
+            Ok(None)
+        } else if existing.is_dir() {
+            Ok(Some(Existing::Dir))
+        } else {
+            Ok(Some(Existing::Other))
+        }
+    }
+
+    /// Creates a new object of `file_type` at `path` with `create`, which
+    /// returns `false` if it skipped the object.
+    ///
+    /// An `existing` object is only removed once the new object was created
+    /// next to it, and is then replaced by renaming the new object.
+    fn replace<F>(
+        &self,
+        path: &Path,
+        existing: Existing,
+        file_type: FileType,
+        create: F,
+    ) -> io::Result<bool>
+    where
+        F: FnOnce(&Path) -> io::Result<bool>,
+    {
+        if existing == Existing::Vacant {
+            return create(path);
+        }
+        let temp = path.with_file_name(format!(".cpio-{}.tmp", std::process::id()));
+        if !create(&temp)? {
+            return Ok(false);
+        }
+        // Renaming only replaces directories by directories, and other
+        // objects by other objects.
+        let is_dir = file_type == FileType::Directory;
+        let res = match existing {
+            Existing::Dir if !is_dir => fs::remove_dir(path),
+            Existing::Other if is_dir => fs::remove_file(path),
+            _ => Ok(()),
+        }
+        .and_then(|()| fs::rename(&temp, path));
+        if res.is_err() {
+            let _ = if is_dir {
+                fs::remove_dir(&temp)
+            } else {
+                fs::remove_file(&temp)
+            };
+        }
+        res.map(|()| true)
+    }
+
+    fn apply_metadata(&self, path: &Path, metadata: &Metadata) -> io::Result<()> {
+        if self.preserve_ownership {
+            lchown(path, Some(metadata.uid), Some(metadata.gid))?;
+        }
+        // Changing the owner may clear the setuid and setgid bits, so the
+        // mode is set afterwards. Symbolic links have no mode of their own.
+        if !metadata.is_symlink() {
+            fs::set_permissions(path, Permissions::from_mode(metadata.mode & 0o7777))?;
+        }
+        if self.preserve_mtime {
+            let mtime = TimeSpec::new(metadata.mtime.into(), 0);
+            utimensat(None, path, &mtime, &mtime, UtimensatFlags::NoFollowSymlink)?;
+        }
+        Ok(())
+    }
+}
+
+/// The object existing at the path of a new object.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Existing {
+    /// There is none.
+    Vacant,
+    /// A directory, which is kept if the new object is a directory.
+    Dir,
+    /// Another object.
+    Other,
+}
+
+/// The first extracted object of a hard link set.
+struct Link<'a> {
+    /// The normalized name.
+    name: Cow<'a, str>,
+    file_type: FileType,
+    /// Whether the data of the link set was written.
+    has_data: bool,
+}
+
+/// The error returned when an object would be extracted outside the
+/// destination directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsafePathError {
+    /// The name of the offending object, as stored in the archive.
+    pub name: String,
+    /// Why the path is unsafe.
+    pub reason: UnsafePathReason,
+}
+
+/// The reason of an [`UnsafePathError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsafePathReason {
+    /// The path contains a `..` component.
+    ParentDir,
+    /// The path leads through a symbolic link.
+    Symlink,
+    /// The object is a hard link to an object of another file type.
+    HardLink,
+}
+
+impl fmt::Display for UnsafePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            UnsafePathReason::ParentDir => "path contains `..`",
+            UnsafePathReason::Symlink => "path leads through a symbolic link",
+            UnsafePathReason::HardLink => "hard link to an object of another file type",
+        };
+        write!(f, "refusing to extract {:?}: {}", self.name, reason)
+    }
+}
+
+impl std::error::Error for UnsafePathError {}
+
+impl From<UnsafePathError> for io::Error {
+    fn from(err: UnsafePathError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Writes the data of a regular file, without following a symbolic link at
+/// `path`. The file is created if `create` is set, and truncated otherwise.
+fn write_file(path: &Path, data: &[u8], create: bool) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).custom_flags(OFlag::O_NOFOLLOW.bits());
+    if create {
+        options.create_new(true);
+    } else {
+        options.truncate(true);
+    }
+    options.open(path)?.write_all(data)
+}
+
+fn mknod(path: &Path, file_type: FileType, metadata: &Metadata) -> io::Result<()> {
+    let kind = SFlag::from_bits_truncate(file_type.mode_bits() as _);
+    let perm = Mode::from_bits_truncate((metadata.mode & 0o7777) as _);
+    nix::sys::stat::mknod(path, kind, perm, makedev(metadata)?)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn makedev(metadata: &Metadata) -> io::Result<nix::sys::stat::dev_t> {
+    let major = u64::from(metadata.rdev_major);
+    let minor = u64::from(metadata.rdev_minor);
+    Ok(nix::sys::stat::makedev(major, minor))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn makedev(metadata: &Metadata) -> io::Result<nix::sys::stat::dev_t> {
+    if metadata.rdev_major == 0 && metadata.rdev_minor == 0 {
+        Ok(0)
+    } else {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
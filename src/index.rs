@@ -0,0 +1,174 @@
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, collections::BTreeMap, string::String};
+
+use crate::{inner, Cursor, Format, Object, ReadError};
+
+/// An index for looking up objects by path, backed by a caller-provided
+/// slice of offsets.
+///
+/// The archive is scanned once on creation, lookups are `O(log n)`. Paths
+/// are normalized, so `"init"`, `"./init"` and `"/init"` are the same, as
+/// are `"etc/passwd"` and `"etc//./passwd"`. If a path appears more than
+/// once, the last object wins, as in the Linux kernel.
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{CpioIndex, CpioNewcWriter, Metadata};
+///
+/// let mut buf = [0u8; 512];
+/// let mut writer = CpioNewcWriter::new(&mut buf);
+/// writer.write(&Metadata::default(), "./init", b"#!/bin/sh").unwrap();
+/// writer.write(&Metadata::default(), "./etc", &[]).unwrap();
+/// let len = writer.finish().unwrap();
+///
+/// let mut offsets = [0; 16];
+/// let index = CpioIndex::new(&buf[..len], &mut offsets).unwrap();
+/// assert_eq!(index.get("/init").unwrap().data, b"#!/bin/sh");
+/// assert!(index.get("/sbin/init").is_none());
+/// ```
+pub struct CpioIndex<'a, 'o> {
+    buf: &'a [u8],
+    format: Format,
+    /// Offsets of the objects, sorted by normalized path, then by position.
+    offsets: &'o [usize],
+}
+
+impl<'a, 'o> CpioIndex<'a, 'o> {
+    /// Scans the archive in the buffer, storing the object offsets in
+    /// `offsets`.
+    ///
+    /// Returns [`ReadError::TooManyObjects`] if `offsets` is too short.
+    pub fn new(buf: &'a [u8], offsets: &'o mut [usize]) -> Result<Self, ReadError> {
+        let format = Format::detect(buf).ok_or(ReadError::InvalidMagic)?;
+        let mut cursor = Cursor::new(buf, format);
+        let mut len = 0;
+        loop {
+            let offset = cursor.consumed();
+            match cursor.next() {
+                Some(obj) => obj?,
+                None => break,
+            };
+            *offsets.get_mut(len).ok_or(ReadError::TooManyObjects)? = offset;
+            len += 1;
+        }
+
+        let offsets = &mut offsets[..len];
+        let path_at = |offset: usize| components(object_at(buf, format, offset).name);
+        offsets.sort_unstable_by(|&a, &b| path_at(a).cmp(path_at(b)).then(a.cmp(&b)));
+        Ok(Self {
+            buf,
+            format,
+            offsets,
+        })
+    }
+
+    /// Returns the object at `path`.
+    pub fn get(&self, path: &str) -> Option<Object<'a>> {
+        let end = self
+            .offsets
+            .partition_point(|&offset| components(self.object(offset).name).le(components(path)));
+        let obj = self.object(*self.offsets[..end].last()?);
+        if components(obj.name).eq(components(path)) {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of indexed objects, including duplicates.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns `true` if the archive contains no objects.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    fn object(&self, offset: usize) -> Object<'a> {
+        object_at(self.buf, self.format, offset)
+    }
+}
+
+/// Parses the object at `offset`, which is known to be valid.
+fn object_at(buf: &[u8], format: Format, offset: usize) -> Object<'_> {
+    inner(&mut &buf[offset..], format, false).expect("indexed object is valid")
+}
+
+/// An index for looking up objects by path, backed by a `BTreeMap`.
+///
+/// Paths are normalized the same way as in [`CpioIndex`].
+#[cfg(feature = "alloc")]
+pub struct CpioMapIndex<'a> {
+    objects: BTreeMap<Cow<'a, str>, Object<'a>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> CpioMapIndex<'a> {
+    /// Scans the archive in the buffer.
+    pub fn new(buf: &'a [u8]) -> Result<Self, ReadError> {
+        let format = Format::detect(buf).ok_or(ReadError::InvalidMagic)?;
+        let mut cursor = Cursor::new(buf, format);
+        let mut objects = BTreeMap::new();
+        while let Some(obj) = cursor.next() {
+            let obj = obj?;
+            objects.insert(normalize(obj.name), obj);
+        }
+        Ok(Self { objects })
+    }
+
+    /// Returns the object at `path`.
+    pub fn get(&self, path: &str) -> Option<Object<'a>> {
+        self.objects.get(&*normalize(path)).copied()
+    }
+
+    /// Returns the number of indexed objects.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns `true` if the archive contains no objects.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+/// Returns the components of a path, skipping empty and `.` components.
+pub(crate) fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|c| !c.is_empty() && *c != ".")
+}
+
+/// Normalizes a path by joining its [`components`], so that leading `/` and
+/// `./`, trailing `/`, and empty and `.` components are dropped.
+///
+/// The root directory is normalized to the empty string. The path is only
+/// copied if components are dropped between others, as in `etc//passwd`.
+#[cfg(feature = "alloc")]
+pub(crate) fn normalize(path: &str) -> Cow<'_, str> {
+    let mut trimmed = path;
+    loop {
+        if let Some(rest) = trimmed.strip_prefix("./") {
+            trimmed = rest;
+        } else if let Some(rest) = trimmed.strip_prefix('/') {
+            trimmed = rest;
+        } else {
+            break;
+        }
+    }
+    let trimmed = match trimmed.trim_end_matches('/') {
+        "." => "",
+        trimmed => trimmed,
+    };
+    if trimmed.is_empty() || trimmed.split('/').eq(components(trimmed)) {
+        return Cow::Borrowed(trimmed);
+    }
+    let mut normalized = String::with_capacity(trimmed.len());
+    for component in components(trimmed) {
+        if !normalized.is_empty() {
+            normalized.push('/');
+        }
+        normalized.push_str(component);
+    }
+    Cow::Owned(normalized)
+}
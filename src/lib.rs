@@ -1,7 +1,137 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+#![forbid(unsafe_code)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+use core::convert::TryFrom;
+use core::fmt;
+
+#[cfg(all(
+    feature = "std",
+    any(feature = "gzip", feature = "zstd", feature = "xz", feature = "lz4")
+))]
+mod compress;
+#[cfg(all(feature = "std", unix))]
+mod create;
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz", feature = "lz4"))]
+mod decompress;
+mod early;
+#[cfg(all(feature = "std", unix))]
+mod extract;
+mod index;
+#[cfg(feature = "alloc")]
+mod link;
+mod mode;
+mod spec;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "alloc")]
+mod tree;
+mod writer;
+
+#[cfg(all(
+    feature = "std",
+    any(feature = "gzip", feature = "zstd", feature = "xz", feature = "lz4")
+))]
+pub use compress::Compressor;
+#[cfg(all(feature = "std", unix))]
+pub use create::{Archiver, Mtime};
+#[cfg(all(
+    feature = "alloc",
+    any(feature = "gzip", feature = "zstd", feature = "xz", feature = "lz4")
+))]
+pub use decompress::decompress;
+#[cfg(all(
+    feature = "std",
+    any(feature = "gzip", feature = "zstd", feature = "xz", feature = "lz4")
+))]
+pub use decompress::Decompressor;
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz", feature = "lz4"))]
+pub use decompress::{decompress_into, Compression};
+pub use early::{find_cpio_data, CpioData, FindCpioData};
+#[cfg(all(feature = "std", unix))]
+pub use extract::{Extractor, Overwrite, UnsafePathError, UnsafePathReason};
+pub use index::CpioIndex;
+#[cfg(feature = "alloc")]
+pub use index::CpioMapIndex;
+#[cfg(feature = "alloc")]
+pub use link::HardLinks;
+pub use mode::FileType;
+#[cfg(feature = "alloc")]
+pub use spec::dump_spec;
+pub use spec::{Spec, SpecEntry, SpecError, SpecErrorReason};
+
+#[cfg(feature = "std")]
+pub use stream::{CpioStreamReader, CpioStreamWriter, StreamObject};
+#[cfg(feature = "alloc")]
+pub use tree::{CpioTree, DirEntry, ReadDir};
+pub use writer::{CpioNewcWriter, WriteError};
+
+const NEWC_HEADER_LEN: usize = 110;
+const NEWC_MAGIC: &[u8] = b"070701";
+const CRC_MAGIC: &[u8] = b"070702";
+const ODC_HEADER_LEN: usize = 76;
+const ODC_MAGIC: &[u8] = b"070707";
+const BIN_HEADER_LEN: usize = 26;
+const BIN_MAGIC: u16 = 0o070707;
+const TRAILER_NAME: &str = "TRAILER!!!";
+
+/// Implements the methods shared by the buffer readers, which all wrap a
+/// [`Cursor`].
+macro_rules! impl_reader {
+    ($reader:ident) => {
+        impl<'a> $reader<'a> {
+            /// Sets whether to continue reading after a trailer, like the
+            /// Linux initramfs loader does.
+            ///
+            /// In this mode, NUL padding between archives is skipped, and the
+            /// iteration ends at the end of the buffer.
+            pub fn concatenated(mut self, concatenated: bool) -> Self {
+                self.cursor.concatenated = concatenated;
+                self
+            }
+
+            /// Returns the index of the archive the last object was read
+            /// from, or 0 if no object was read yet.
+            ///
+            /// This is always 0 unless reading
+            /// [`concatenated`](Self::concatenated) archives. Trailers do not
+            /// change it, so after the iteration ends it is the index of the
+            /// archive holding the last object.
+            pub fn segment(&self) -> usize {
+                self.cursor.segment
+            }
+
+            /// Returns the bytes not consumed by the reader.
+            ///
+            /// After the iteration ends, these are the bytes following the
+            /// trailer. If an error occurred, they start with the invalid
+            /// object.
+            pub fn remaining(&self) -> &'a [u8] {
+                self.cursor.buf
+            }
+
+            /// Returns the number of bytes consumed by the reader.
+            pub fn consumed(&self) -> usize {
+                self.cursor.consumed()
+            }
+        }
+
+        impl<'a> Iterator for $reader<'a> {
+            type Item = Result<Object<'a>, ReadError>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.cursor.next()
+            }
+        }
+    };
+}
 
 /// A CPIO file (newc format) reader.
 ///
+/// Archives in the newc-with-CRC format (magic `070702`) are read as well.
+///
 /// # Example
 ///
 /// ```rust,should_panic
@@ -12,18 +142,197 @@
 ///     println!("{}", obj.unwrap().name);    
 /// }
 /// ```
+///
+/// Objects borrow from the buffer, not from the reader, so they can be kept
+/// after the reader is dropped:
+///
+/// ```rust
+/// use cpio::{CpioNewcReader, CpioNewcWriter, Metadata};
+///
+/// let mut buf = [0u8; 256];
+/// let mut writer = CpioNewcWriter::new(&mut buf);
+/// writer.write(&Metadata::default(), "init", b"#!/bin/sh").unwrap();
+/// let len = writer.finish().unwrap();
+///
+/// let init = CpioNewcReader::new(&buf[..len]).next().unwrap().unwrap();
+/// assert_eq!(init.data, b"#!/bin/sh");
+/// ```
 pub struct CpioNewcReader<'a> {
-    buf: &'a [u8],
+    cursor: Cursor<'a>,
 }
 
 impl<'a> CpioNewcReader<'a> {
     /// Creates a new CPIO reader on the buffer.
     pub fn new(buf: &'a [u8]) -> Self {
-        Self { buf }
+        Self {
+            cursor: Cursor::new(buf, Format::Newc),
+        }
+    }
+
+    /// Sets whether to verify the checksum of regular files in the
+    /// newc-with-CRC format.
+    ///
+    /// On mismatch, [`ReadError::ChecksumMismatch`] is returned.
+    pub fn verify_checksum(mut self, verify: bool) -> Self {
+        self.cursor.verify_checksum = verify;
+        self
+    }
+}
+
+impl_reader!(CpioNewcReader);
+
+/// A CPIO file (portable ASCII "odc" format) reader.
+///
+/// The 18-bit device numbers are split into major and minor numbers using
+/// the traditional 8-bit minor encoding.
+///
+/// # Example
+///
+/// ```rust,should_panic
+/// use cpio::CpioOdcReader;
+///
+/// let reader = CpioOdcReader::new(&[]);
+/// for obj in reader {
+///     println!("{}", obj.unwrap().name);
+/// }
+/// ```
+pub struct CpioOdcReader<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> CpioOdcReader<'a> {
+    /// Creates a new CPIO reader on the buffer.
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            cursor: Cursor::new(buf, Format::Odc),
+        }
+    }
+}
+
+impl_reader!(CpioOdcReader);
+
+/// A CPIO file (old binary format) reader.
+///
+/// Both little-endian and big-endian archives are supported, the byte order
+/// is detected from the magic number of each header.
+///
+/// # Example
+///
+/// ```rust,should_panic
+/// use cpio::CpioBinReader;
+///
+/// let reader = CpioBinReader::new(&[]);
+/// for obj in reader {
+///     println!("{}", obj.unwrap().name);
+/// }
+/// ```
+pub struct CpioBinReader<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> CpioBinReader<'a> {
+    /// Creates a new CPIO reader on the buffer.
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            cursor: Cursor::new(buf, Format::BinLe),
+        }
+    }
+}
+
+impl_reader!(CpioBinReader);
+
+/// A CPIO file reader which detects the format from the first magic number.
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{CpioNewcWriter, CpioReader, Format, Metadata};
+///
+/// let mut buf = [0u8; 256];
+/// let mut writer = CpioNewcWriter::new(&mut buf);
+/// writer.write(&Metadata::default(), "empty", &[]).unwrap();
+/// let len = writer.finish().unwrap();
+///
+/// let reader = CpioReader::new(&buf[..len]).unwrap();
+/// assert_eq!(reader.format(), Format::Newc);
+/// for obj in reader {
+///     println!("{}", obj.unwrap().name);
+/// }
+/// ```
+pub struct CpioReader<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> CpioReader<'a> {
+    /// Creates a new CPIO reader on the buffer.
+    ///
+    /// Returns [`ReadError::UnknownFormat`] with the leading bytes of the
+    /// buffer if no known magic number is found.
+    pub fn new(buf: &'a [u8]) -> Result<Self, ReadError> {
+        let format = Format::detect(buf).ok_or_else(|| {
+            let mut magic = [0; 6];
+            let len = buf.len().min(magic.len());
+            magic[..len].copy_from_slice(&buf[..len]);
+            ReadError::UnknownFormat(magic)
+        })?;
+        Ok(Self {
+            cursor: Cursor::new(buf, format),
+        })
+    }
+
+    /// Returns the detected format.
+    pub fn format(&self) -> Format {
+        self.cursor.format
+    }
+
+    /// Sets whether to verify the checksum of regular files in the
+    /// newc-with-CRC format.
+    ///
+    /// On mismatch, [`ReadError::ChecksumMismatch`] is returned.
+    pub fn verify_checksum(mut self, verify: bool) -> Self {
+        self.cursor.verify_checksum = verify;
+        self
+    }
+}
+
+impl_reader!(CpioReader);
+
+/// The format of a CPIO file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The new portable ASCII format, magic `070701`.
+    Newc,
+    /// The new portable ASCII format with checksum, magic `070702`.
+    Crc,
+    /// The old portable ASCII format, magic `070707`.
+    Odc,
+    /// The old binary format in little-endian byte order.
+    BinLe,
+    /// The old binary format in big-endian byte order.
+    BinBe,
+}
+
+impl Format {
+    /// Detects the format from the magic number at the start of the buffer.
+    pub fn detect(buf: &[u8]) -> Option<Self> {
+        if buf.starts_with(NEWC_MAGIC) {
+            Some(Format::Newc)
+        } else if buf.starts_with(CRC_MAGIC) {
+            Some(Format::Crc)
+        } else if buf.starts_with(ODC_MAGIC) {
+            Some(Format::Odc)
+        } else if buf.starts_with(&BIN_MAGIC.to_le_bytes()) {
+            Some(Format::BinLe)
+        } else if buf.starts_with(&BIN_MAGIC.to_be_bytes()) {
+            Some(Format::BinBe)
+        } else {
+            None
+        }
     }
 }
 
 /// File system object in CPIO file.
+#[derive(Debug, Clone, Copy)]
 pub struct Object<'a> {
     /// The file metadata.
     pub metadata: Metadata,
@@ -33,32 +342,200 @@ pub struct Object<'a> {
     pub data: &'a [u8],
 }
 
-impl<'a> Iterator for CpioNewcReader<'a> {
-    type Item = Result<Object<'a>, ReadError>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        // SAFETY: To workaround lifetime
-        let s: &'a mut Self = unsafe { core::mem::transmute(self) };
-        match inner(&mut s.buf) {
-            Ok(Object {
-                name: "TRAILER!!!", ..
-            }) => None,
-            res => Some(res),
+impl<'a> Object<'a> {
+    /// Returns the target path if this is a symbolic link.
+    ///
+    /// Returns [`ReadError::InvalidSymlink`] if the target is empty, contains
+    /// NUL or is not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cpio::{CpioNewcReader, CpioNewcWriter, Metadata};
+    ///
+    /// let mut buf = [0u8; 512];
+    /// let mut writer = CpioNewcWriter::new(&mut buf);
+    /// let metadata = Metadata {
+    ///     mode: 0o120777,
+    ///     ..Default::default()
+    /// };
+    /// writer.write(&metadata, "sbin/init", b"/lib/systemd/systemd").unwrap();
+    /// let len = writer.finish().unwrap();
+    ///
+    /// let obj = CpioNewcReader::new(&buf[..len]).next().unwrap().unwrap();
+    /// assert_eq!(obj.symlink_target(), Ok(Some("/lib/systemd/systemd")));
+    /// ```
+    pub fn symlink_target(&self) -> Result<Option<&'a str>, ReadError> {
+        if !self.metadata.is_symlink() {
+            return Ok(None);
+        }
+        if self.data.is_empty() || self.data.contains(&0) {
+            return Err(ReadError::InvalidSymlink);
         }
+        let target = core::str::from_utf8(self.data).map_err(|_| ReadError::InvalidSymlink)?;
+        Ok(Some(target))
     }
 }
 
-fn inner<'a>(buf: &'a mut &'a [u8]) -> Result<Object<'a>, ReadError> {
-    const HEADER_LEN: usize = 110;
-    const MAGIC_NUMBER: &[u8] = b"070701";
+/// The reading state shared by the buffer readers.
+struct Cursor<'a> {
+    buf: &'a [u8],
+    len: usize,
+    format: Format,
+    verify_checksum: bool,
+    concatenated: bool,
+    /// Index of the archive the last object was read from.
+    segment: usize,
+    /// Number of trailers read so far.
+    trailers: usize,
+    /// Whether objects of the current segment were read but not its trailer.
+    in_segment: bool,
+    finished: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buf: &'a [u8], format: Format) -> Self {
+        Self {
+            buf,
+            len: buf.len(),
+            format,
+            verify_checksum: false,
+            concatenated: false,
+            segment: 0,
+            trailers: 0,
+            in_segment: false,
+            finished: false,
+        }
+    }
+
+    fn consumed(&self) -> usize {
+        self.len - self.buf.len()
+    }
+
+    fn next(&mut self) -> Option<Result<Object<'a>, ReadError>> {
+        while !self.finished {
+            if self.concatenated {
+                let padding = self.buf.iter().take_while(|&&b| b == 0).count();
+                self.buf = &self.buf[padding..];
+            }
+            if self.buf.is_empty() {
+                self.finished = true;
+                if self.concatenated && !self.in_segment {
+                    break;
+                }
+                return Some(Err(ReadError::MissingTrailer));
+            }
+            let start = self.buf;
+            match inner(&mut self.buf, self.format, self.verify_checksum) {
+                Ok(Object {
+                    name: TRAILER_NAME, ..
+                }) => {
+                    self.in_segment = false;
+                    self.trailers += 1;
+                    self.finished = !self.concatenated;
+                }
+                Ok(obj) => {
+                    self.in_segment = true;
+                    self.segment = self.trailers;
+                    return Some(Ok(obj));
+                }
+                Err(err) => {
+                    // Keep the invalid object in the remaining bytes.
+                    self.buf = start;
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+}
 
-    if buf.len() < HEADER_LEN {
+fn inner<'a>(
+    buf: &mut &'a [u8],
+    format: Format,
+    verify_checksum: bool,
+) -> Result<Object<'a>, ReadError> {
+    if buf.len() < format.header_len() {
         return Err(ReadError::BufTooShort);
     }
-    let magic = buf.read_bytes(6)?;
-    if magic != MAGIC_NUMBER {
-        return Err(ReadError::InvalidMagic);
+    let format = Format::detect(buf)
+        .filter(|f| f.is_compatible(format))
+        .ok_or(ReadError::InvalidMagic)?;
+    let header = buf.read_bytes(format.header_len())?;
+    let (metadata, name_size) = format.parse_header(header)?;
+    let name = buf.read_name(name_size)?;
+    buf.read_bytes(format.name_padding(name_size))?;
+
+    let data = buf.read_bytes(metadata.file_size as usize)?;
+    buf.read_bytes(format.data_padding(metadata.file_size))?;
+
+    if verify_checksum
+        && format == Format::Crc
+        && metadata.is_file()
+        && checksum(data) != metadata.check
+    {
+        return Err(ReadError::ChecksumMismatch);
+    }
+
+    Ok(Object {
+        metadata,
+        name,
+        data,
+    })
+}
+
+impl Format {
+    fn header_len(self) -> usize {
+        match self {
+            Format::Newc | Format::Crc => NEWC_HEADER_LEN,
+            Format::Odc => ODC_HEADER_LEN,
+            Format::BinLe | Format::BinBe => BIN_HEADER_LEN,
+        }
+    }
+
+    /// Returns whether the two formats may be mixed in one archive.
+    fn is_compatible(self, other: Format) -> bool {
+        self.header_len() == other.header_len()
+    }
+
+    fn name_padding(self, name_size: usize) -> usize {
+        match self {
+            Format::Newc | Format::Crc => pad_to_4(NEWC_HEADER_LEN + name_size),
+            Format::Odc => 0,
+            Format::BinLe | Format::BinBe => name_size % 2,
+        }
+    }
+
+    fn data_padding(self, file_size: u32) -> usize {
+        match self {
+            Format::Newc | Format::Crc => pad_to_4(file_size as usize),
+            Format::Odc => 0,
+            Format::BinLe | Format::BinBe => file_size as usize % 2,
+        }
+    }
+
+    /// Parses a header of `header_len` bytes, returning the metadata and the
+    /// size of the name including the trailing NUL.
+    fn parse_header(self, mut buf: &[u8]) -> Result<(Metadata, usize), ReadError> {
+        match self {
+            Format::Newc | Format::Crc => {
+                buf.read_bytes(6)?;
+                parse_newc_header(buf)
+            }
+            Format::Odc => {
+                buf.read_bytes(6)?;
+                parse_odc_header(buf)
+            }
+            Format::BinLe | Format::BinBe => {
+                buf.read_bytes(2)?;
+                parse_bin_header(buf, self == Format::BinBe)
+            }
+        }
     }
+}
+
+fn parse_newc_header(mut buf: &[u8]) -> Result<(Metadata, usize), ReadError> {
     let ino = buf.read_hex_u32()?;
     let mode = buf.read_hex_u32()?;
     let uid = buf.read_hex_u32()?;
@@ -71,7 +548,7 @@ fn inner<'a>(buf: &'a mut &'a [u8]) -> Result<Object<'a>, ReadError> {
     let rdev_major = buf.read_hex_u32()?;
     let rdev_minor = buf.read_hex_u32()?;
     let name_size = buf.read_hex_u32()? as usize;
-    let _check = buf.read_hex_u32()?;
+    let check = buf.read_hex_u32()?;
     let metadata = Metadata {
         ino,
         mode,
@@ -84,28 +561,74 @@ fn inner<'a>(buf: &'a mut &'a [u8]) -> Result<Object<'a>, ReadError> {
         dev_minor,
         rdev_major,
         rdev_minor,
+        check,
     };
-    let name_with_nul = buf.read_bytes(name_size)?;
-    if name_with_nul.last() != Some(&0) {
-        return Err(ReadError::InvalidName);
-    }
-    let name = core::str::from_utf8(&name_with_nul[..name_size - 1])
-        .map_err(|_| ReadError::InvalidName)?;
-    buf.read_bytes(pad_to_4(HEADER_LEN + name_size))?;
+    Ok((metadata, name_size))
+}
 
-    let data = buf.read_bytes(file_size as usize)?;
-    buf.read_bytes(pad_to_4(file_size as usize))?;
+fn parse_odc_header(mut buf: &[u8]) -> Result<(Metadata, usize), ReadError> {
+    let dev = buf.read_oct_u32(6)?;
+    let ino = buf.read_oct_u32(6)?;
+    let mode = buf.read_oct_u32(6)?;
+    let uid = buf.read_oct_u32(6)?;
+    let gid = buf.read_oct_u32(6)?;
+    let nlink = buf.read_oct_u32(6)?;
+    let rdev = buf.read_oct_u32(6)?;
+    let mtime = buf.read_oct_u32(11)?;
+    let name_size = buf.read_oct_u32(6)? as usize;
+    let file_size = buf.read_oct_u32(11)?;
+    let metadata = Metadata {
+        ino,
+        mode,
+        uid,
+        gid,
+        nlink,
+        mtime,
+        file_size,
+        dev_major: dev >> 8,
+        dev_minor: dev & 0xff,
+        rdev_major: rdev >> 8,
+        rdev_minor: rdev & 0xff,
+        check: 0,
+    };
+    Ok((metadata, name_size))
+}
 
-    Ok(Object {
-        metadata,
-        name,
-        data,
-    })
+fn parse_bin_header(mut buf: &[u8], big_endian: bool) -> Result<(Metadata, usize), ReadError> {
+    let dev = buf.read_u16(big_endian)?;
+    let ino = buf.read_u16(big_endian)?;
+    let mode = buf.read_u16(big_endian)?;
+    let uid = buf.read_u16(big_endian)?;
+    let gid = buf.read_u16(big_endian)?;
+    let nlink = buf.read_u16(big_endian)?;
+    let rdev = buf.read_u16(big_endian)?;
+    let mtime = buf.read_u16_pair(big_endian)?;
+    let name_size = buf.read_u16(big_endian)? as usize;
+    let file_size = buf.read_u16_pair(big_endian)?;
+    let metadata = Metadata {
+        ino,
+        mode,
+        uid,
+        gid,
+        nlink,
+        mtime,
+        file_size,
+        dev_major: dev >> 8,
+        dev_minor: dev & 0xff,
+        rdev_major: rdev >> 8,
+        rdev_minor: rdev & 0xff,
+        check: 0,
+    };
+    Ok((metadata, name_size))
 }
 
 trait BufExt<'a> {
     fn read_hex_u32(&mut self) -> Result<u32, ReadError>;
+    fn read_oct_u32(&mut self, len: usize) -> Result<u32, ReadError>;
+    fn read_u16(&mut self, big_endian: bool) -> Result<u32, ReadError>;
+    fn read_u16_pair(&mut self, big_endian: bool) -> Result<u32, ReadError>;
     fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ReadError>;
+    fn read_name(&mut self, name_size: usize) -> Result<&'a str, ReadError>;
 }
 
 impl<'a> BufExt<'a> for &'a [u8] {
@@ -117,6 +640,32 @@ impl<'a> BufExt<'a> for &'a [u8] {
         Ok(value)
     }
 
+    fn read_oct_u32(&mut self, len: usize) -> Result<u32, ReadError> {
+        let (oct, rest) = self.split_at(len);
+        *self = rest;
+        let str = core::str::from_utf8(oct).map_err(|_| ReadError::InvalidASCII)?;
+        let value = u64::from_str_radix(str, 8).map_err(|_| ReadError::InvalidASCII)?;
+        u32::try_from(value).map_err(|_| ReadError::Overflow)
+    }
+
+    fn read_u16(&mut self, big_endian: bool) -> Result<u32, ReadError> {
+        let bytes = self.read_bytes(2)?;
+        let bytes = [bytes[0], bytes[1]];
+        let value = if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        };
+        Ok(u32::from(value))
+    }
+
+    /// Reads a 32-bit value stored as two 16-bit halves, most significant first.
+    fn read_u16_pair(&mut self, big_endian: bool) -> Result<u32, ReadError> {
+        let high = self.read_u16(big_endian)?;
+        let low = self.read_u16(big_endian)?;
+        Ok(high << 16 | low)
+    }
+
     fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ReadError> {
         if self.len() < len {
             return Err(ReadError::BufTooShort);
@@ -125,6 +674,14 @@ impl<'a> BufExt<'a> for &'a [u8] {
         *self = rest;
         Ok(bytes)
     }
+
+    fn read_name(&mut self, name_size: usize) -> Result<&'a str, ReadError> {
+        let name_with_nul = self.read_bytes(name_size)?;
+        if name_with_nul.last() != Some(&0) {
+            return Err(ReadError::InvalidName);
+        }
+        core::str::from_utf8(&name_with_nul[..name_size - 1]).map_err(|_| ReadError::InvalidName)
+    }
 }
 
 /// pad out to a multiple of 4 bytes
@@ -135,6 +692,13 @@ fn pad_to_4(len: usize) -> usize {
     }
 }
 
+/// Computes the checksum used by the newc-with-CRC format: the sum of all
+/// data bytes, truncated to 32 bits.
+fn checksum(data: &[u8]) -> u32 {
+    data.iter()
+        .fold(0u32, |sum, &byte| sum.wrapping_add(u32::from(byte)))
+}
+
 /// The error type which is returned from CPIO reader.
 #[derive(Debug, PartialEq, Eq)]
 pub enum ReadError {
@@ -142,10 +706,44 @@ pub enum ReadError {
     InvalidMagic,
     InvalidName,
     BufTooShort,
+    ChecksumMismatch,
+    Overflow,
+    UnknownFormat([u8; 6]),
+    MissingTrailer,
+    InvalidFileType,
+    InvalidSymlink,
+    TooManyObjects,
+    InvalidCompressedData,
+    OutputTooShort,
+    UnsupportedCompression,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::InvalidASCII => write!(f, "invalid ASCII field in header"),
+            ReadError::InvalidMagic => write!(f, "invalid magic number"),
+            ReadError::InvalidName => write!(f, "invalid file name"),
+            ReadError::BufTooShort => write!(f, "unexpected end of buffer"),
+            ReadError::ChecksumMismatch => write!(f, "data checksum mismatch"),
+            ReadError::Overflow => write!(f, "header field out of range"),
+            ReadError::UnknownFormat(magic) => write!(f, "unknown format, magic {:02x?}", magic),
+            ReadError::MissingTrailer => write!(f, "archive ends without trailer"),
+            ReadError::InvalidFileType => write!(f, "invalid file type"),
+            ReadError::InvalidSymlink => write!(f, "invalid symbolic link target"),
+            ReadError::TooManyObjects => write!(f, "too many objects"),
+            ReadError::InvalidCompressedData => write!(f, "invalid compressed data"),
+            ReadError::OutputTooShort => write!(f, "output buffer too short"),
+            ReadError::UnsupportedCompression => write!(f, "compression format not enabled"),
+        }
+    }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for ReadError {}
+
 /// The file metadata.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Metadata {
     pub ino: u32,
     pub mode: u32,
@@ -158,4 +756,27 @@ pub struct Metadata {
     pub dev_minor: u32,
     pub rdev_major: u32,
     pub rdev_minor: u32,
+    /// The data checksum. Only meaningful in the newc-with-CRC format.
+    pub check: u32,
+}
+
+impl Metadata {
+    /// Returns the device number of the device containing the file, encoded
+    /// like the Linux kernel's `MKDEV`.
+    pub fn dev(&self) -> u32 {
+        mkdev(self.dev_major, self.dev_minor)
+    }
+
+    /// Returns the device number of a character or block device, encoded
+    /// like the Linux kernel's `MKDEV`.
+    pub fn rdev(&self) -> u32 {
+        mkdev(self.rdev_major, self.rdev_minor)
+    }
+}
+
+/// Combines major and minor device numbers like the Linux kernel's `MKDEV`,
+/// with 20 bits for the minor number.
+fn mkdev(major: u32, minor: u32) -> u32 {
+    const MINORBITS: u32 = 20;
+    major << MINORBITS | (minor & ((1 << MINORBITS) - 1))
 }
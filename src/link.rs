@@ -0,0 +1,109 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::{CpioReader, Metadata, Object, ReadError};
+
+/// Hard links in an archive, grouped by device and inode number.
+///
+/// Hard links are stored as multiple objects sharing `ino`, `dev_major` and
+/// `dev_minor`, with `nlink > 1`. GNU cpio stores the data only on the last
+/// of them, leaving the others empty; [`resolve`](Self::resolve) finds the
+/// object which actually carries it. Directories are never grouped.
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{CpioNewcReader, CpioNewcWriter, HardLinks, Metadata};
+///
+/// let mut buf = [0u8; 512];
+/// let mut writer = CpioNewcWriter::new(&mut buf);
+/// let metadata = Metadata {
+///     ino: 42,
+///     mode: 0o100755,
+///     nlink: 2,
+///     ..Default::default()
+/// };
+/// writer.write(&metadata, "bin/sh", &[]).unwrap();
+/// writer.write(&metadata, "bin/busybox", b"\x7fELF").unwrap();
+/// let len = writer.finish().unwrap();
+///
+/// let links = HardLinks::new(&buf[..len]).unwrap();
+/// let sh = CpioNewcReader::new(&buf[..len]).next().unwrap().unwrap();
+/// assert_eq!(links.links(&sh.metadata).len(), 2);
+/// assert_eq!(links.resolve(sh).data, b"\x7fELF");
+/// ```
+pub struct HardLinks<'a> {
+    groups: BTreeMap<LinkKey, Vec<Object<'a>>>,
+}
+
+/// `(dev_major, dev_minor, ino)`
+type LinkKey = (u32, u32, u32);
+
+impl<'a> HardLinks<'a> {
+    /// Groups the hard links in the archive in the buffer.
+    pub fn new(buf: &'a [u8]) -> Result<Self, ReadError> {
+        Self::from_objects(CpioReader::new(buf)?)
+    }
+
+    /// Groups the hard links among the objects yielded by a reader.
+    pub fn from_objects<I>(objects: I) -> Result<Self, ReadError>
+    where
+        I: IntoIterator<Item = Result<Object<'a>, ReadError>>,
+    {
+        let mut groups = BTreeMap::new();
+        for obj in objects {
+            let obj = obj?;
+            if let Some(key) = link_key(&obj.metadata) {
+                groups.entry(key).or_insert_with(Vec::new).push(obj);
+            }
+        }
+        // An object with `nlink > 1` whose other links are not in the
+        // archive is not a link set.
+        groups.retain(|_, links: &mut Vec<_>| links.len() > 1);
+        Ok(Self { groups })
+    }
+
+    /// Returns all objects linked to the object with `metadata`, in archive
+    /// order, or an empty slice if it is not a hard link.
+    pub fn links(&self, metadata: &Metadata) -> &[Object<'a>] {
+        link_key(metadata)
+            .and_then(|key| self.groups.get(&key))
+            .map_or(&[], |links| links)
+    }
+
+    /// Returns the object with its data taken from the link which carries
+    /// it.
+    ///
+    /// Objects which are not hard links are returned unchanged.
+    pub fn resolve(&self, obj: Object<'a>) -> Object<'a> {
+        match self
+            .links(&obj.metadata)
+            .iter()
+            .rev()
+            .find(|l| !l.data.is_empty())
+        {
+            Some(carrier) => Object {
+                metadata: Metadata {
+                    file_size: carrier.metadata.file_size,
+                    ..obj.metadata
+                },
+                data: carrier.data,
+                ..obj
+            },
+            None => obj,
+        }
+    }
+
+    /// Returns an iterator over the link sets.
+    pub fn iter(&self) -> impl Iterator<Item = &[Object<'a>]> {
+        self.groups.values().map(|links| links.as_slice())
+    }
+}
+
+fn link_key(metadata: &Metadata) -> Option<LinkKey> {
+    if metadata.nlink > 1 && !metadata.is_dir() {
+        Some((metadata.dev_major, metadata.dev_minor, metadata.ino))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{Metadata, ReadError};
+
+const S_IFMT: u32 = 0o170000;
+const S_IFSOCK: u32 = 0o140000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFREG: u32 = 0o100000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFIFO: u32 = 0o010000;
+
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const S_ISVTX: u32 = 0o1000;
+
+/// The type of a file system object, decoded from the `S_IFMT` bits of the
+/// mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Regular,
+    Directory,
+    Symlink,
+    CharDevice,
+    BlockDevice,
+    Fifo,
+    Socket,
+}
+
+impl FileType {
+    /// Decodes the file type from the mode, returning `None` for invalid
+    /// type bits.
+    pub fn from_mode(mode: u32) -> Option<Self> {
+        match mode & S_IFMT {
+            S_IFREG => Some(FileType::Regular),
+            S_IFDIR => Some(FileType::Directory),
+            S_IFLNK => Some(FileType::Symlink),
+            S_IFCHR => Some(FileType::CharDevice),
+            S_IFBLK => Some(FileType::BlockDevice),
+            S_IFIFO => Some(FileType::Fifo),
+            S_IFSOCK => Some(FileType::Socket),
+            _ => None,
+        }
+    }
+
+    /// Returns the `S_IFMT` bits of the file type.
+    pub fn mode_bits(self) -> u32 {
+        match self {
+            FileType::Regular => S_IFREG,
+            FileType::Directory => S_IFDIR,
+            FileType::Symlink => S_IFLNK,
+            FileType::CharDevice => S_IFCHR,
+            FileType::BlockDevice => S_IFBLK,
+            FileType::Fifo => S_IFIFO,
+            FileType::Socket => S_IFSOCK,
+        }
+    }
+}
+
+impl Metadata {
+    /// Returns the file type.
+    ///
+    /// Returns [`ReadError::InvalidFileType`] if the type bits of the mode
+    /// are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cpio::{FileType, Metadata};
+    ///
+    /// let metadata = Metadata {
+    ///     mode: 0o40755,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(metadata.file_type(), Ok(FileType::Directory));
+    /// assert!(metadata.is_dir());
+    /// assert_eq!(metadata.permissions(), 0o755);
+    /// ```
+    pub fn file_type(&self) -> Result<FileType, ReadError> {
+        FileType::from_mode(self.mode).ok_or(ReadError::InvalidFileType)
+    }
+
+    /// Returns `true` if this is a regular file.
+    pub fn is_file(&self) -> bool {
+        self.mode & S_IFMT == S_IFREG
+    }
+
+    /// Returns `true` if this is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+
+    /// Returns `true` if this is a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.mode & S_IFMT == S_IFLNK
+    }
+
+    /// Returns the permission bits, excluding setuid, setgid and sticky.
+    pub fn permissions(&self) -> u32 {
+        self.mode & 0o777
+    }
+
+    /// Returns `true` if the set-user-ID bit is set.
+    pub fn is_setuid(&self) -> bool {
+        self.mode & S_ISUID != 0
+    }
+
+    /// Returns `true` if the set-group-ID bit is set.
+    pub fn is_setgid(&self) -> bool {
+        self.mode & S_ISGID != 0
+    }
+
+    /// Returns `true` if the sticky bit is set.
+    pub fn is_sticky(&self) -> bool {
+        self.mode & S_ISVTX != 0
+    }
+}
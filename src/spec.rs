@@ -0,0 +1,372 @@
+use core::fmt;
+use core::str::Lines;
+
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::time::UNIX_EPOCH;
+
+#[cfg(feature = "alloc")]
+use crate::index::normalize;
+#[cfg(feature = "std")]
+use crate::CpioStreamWriter;
+use crate::{FileType, Metadata};
+#[cfg(feature = "alloc")]
+use crate::{HardLinks, Object, ReadError};
+
+/// A parser for the file list format of the Linux kernel's
+/// `usr/gen_init_cpio`.
+///
+/// Each line describes one object:
+///
+/// ```text
+/// file <name> <location> <mode> <uid> <gid> [<hard links>]
+/// dir <name> <mode> <uid> <gid>
+/// nod <name> <mode> <uid> <gid> <dev_type> <maj> <min>
+/// slink <name> <target> <mode> <uid> <gid>
+/// pipe <name> <mode> <uid> <gid>
+/// sock <name> <mode> <uid> <gid>
+/// ```
+///
+/// Blank lines and lines starting with `#` are ignored. A leading `/` is
+/// stripped from names. Unlike `gen_init_cpio`, environment variables in
+/// locations are not expanded.
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{CpioNewcReader, CpioNewcWriter, Spec};
+///
+/// let spec = "\
+/// dir /dev 0755 0 0
+/// nod /dev/console 0600 0 0 c 5 1
+/// slink /bin/sh busybox 0777 0 0
+/// ";
+/// let mut buf = [0u8; 512];
+/// let mut writer = CpioNewcWriter::new(&mut buf);
+/// for entry in Spec::new(spec) {
+///     let entry = entry.unwrap();
+///     let data = if entry.metadata.is_symlink() {
+///         entry.location.as_bytes()
+///     } else {
+///         &[]
+///     };
+///     writer.write(&entry.metadata, entry.name, data).unwrap();
+/// }
+/// let len = writer.finish().unwrap();
+///
+/// let console = CpioNewcReader::new(&buf[..len]).nth(1).unwrap().unwrap();
+/// assert_eq!(console.name, "dev/console");
+/// assert_eq!(console.metadata.mode, 0o20600);
+/// assert_eq!(console.metadata.rdev(), (5 << 20) | 1);
+/// ```
+pub struct Spec<'a> {
+    lines: Lines<'a>,
+    line: usize,
+}
+
+/// An object described by a line of a [`Spec`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecEntry<'a> {
+    /// The name in the archive, without leading `/`.
+    pub name: &'a str,
+    /// The file type, mode, owner, link count and, for device nodes, device
+    /// numbers. Inode numbers and modification times are left zero.
+    pub metadata: Metadata,
+    /// The path of the data on disk for `file` lines, the link target for
+    /// `slink` lines, and empty otherwise.
+    pub location: &'a str,
+    links: &'a str,
+}
+
+impl<'a> Spec<'a> {
+    /// Creates a parser of the file list in `text`.
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            lines: text.lines(),
+            line: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Spec<'a> {
+    type Item = Result<SpecEntry<'a>, SpecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?.trim();
+            self.line += 1;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            return Some(parse_line(line).map_err(|reason| SpecError {
+                line: self.line,
+                reason,
+            }));
+        }
+    }
+}
+
+impl<'a> SpecEntry<'a> {
+    /// Returns the names of the additional hard links of a `file` line,
+    /// without leading `/`.
+    pub fn links(&self) -> impl Iterator<Item = &'a str> {
+        self.links.split_whitespace().map(strip_root)
+    }
+}
+
+fn parse_line(mut line: &str) -> Result<SpecEntry<'_>, SpecErrorReason> {
+    let kind = field(&mut line)?;
+    let name = strip_root(field(&mut line)?);
+    let (mut file_type, location) = match kind {
+        "file" => (FileType::Regular, field(&mut line)?),
+        "dir" => (FileType::Directory, ""),
+        "nod" => (FileType::CharDevice, ""),
+        "slink" => (FileType::Symlink, field(&mut line)?),
+        "pipe" => (FileType::Fifo, ""),
+        "sock" => (FileType::Socket, ""),
+        _ => return Err(SpecErrorReason::UnknownType),
+    };
+    let mode = u32::from_str_radix(field(&mut line)?, 8)
+        .map_err(|_| SpecErrorReason::InvalidNumber)?
+        & 0o7777;
+    let uid = number(field(&mut line)?)?;
+    let gid = number(field(&mut line)?)?;
+
+    let mut links = "";
+    let mut metadata = Metadata {
+        uid,
+        gid,
+        nlink: 1,
+        ..Default::default()
+    };
+    match file_type {
+        FileType::Regular => {
+            links = line.trim();
+            metadata.nlink += links.split_whitespace().count() as u32;
+        }
+        FileType::Directory => metadata.nlink = 2,
+        FileType::CharDevice => {
+            file_type = match field(&mut line)? {
+                "c" => FileType::CharDevice,
+                "b" => FileType::BlockDevice,
+                _ => return Err(SpecErrorReason::InvalidDeviceType),
+            };
+            metadata.rdev_major = number(field(&mut line)?)?;
+            metadata.rdev_minor = number(field(&mut line)?)?;
+        }
+        _ => {}
+    }
+    metadata.mode = file_type.mode_bits() | mode;
+    Ok(SpecEntry {
+        name,
+        metadata,
+        location,
+        links,
+    })
+}
+
+/// Splits the next whitespace-separated field off `line`.
+fn field<'a>(line: &mut &'a str) -> Result<&'a str, SpecErrorReason> {
+    let rest = line.trim_start();
+    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    if end == 0 {
+        return Err(SpecErrorReason::MissingField);
+    }
+    *line = &rest[end..];
+    Ok(&rest[..end])
+}
+
+fn number(field: &str) -> Result<u32, SpecErrorReason> {
+    field.parse().map_err(|_| SpecErrorReason::InvalidNumber)
+}
+
+fn strip_root(name: &str) -> &str {
+    name.strip_prefix('/').unwrap_or(name)
+}
+
+/// The error returned when a line of a [`Spec`] is malformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecError {
+    /// The line number, starting from 1.
+    pub line: usize,
+    /// Why the line is malformed.
+    pub reason: SpecErrorReason,
+}
+
+/// The reason of a [`SpecError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecErrorReason {
+    /// The line does not start with a known object type.
+    UnknownType,
+    /// The line has too few fields.
+    MissingField,
+    /// A mode, ID or device number is not a valid number.
+    InvalidNumber,
+    /// The device type of a `nod` line is neither `c` nor `b`.
+    InvalidDeviceType,
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            SpecErrorReason::UnknownType => "unknown object type",
+            SpecErrorReason::MissingField => "missing field",
+            SpecErrorReason::InvalidNumber => "invalid number",
+            SpecErrorReason::InvalidDeviceType => "invalid device type",
+        };
+        write!(f, "line {}: {}", self.line, reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SpecError {}
+
+#[cfg(feature = "std")]
+impl From<SpecError> for io::Error {
+    fn from(err: SpecError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> CpioStreamWriter<W> {
+    /// Appends the objects described by a [`Spec`] file list, like
+    /// `gen_init_cpio`.
+    ///
+    /// The data of `file` lines is read from their locations, and their
+    /// modification times are taken from disk; other objects get a
+    /// modification time of 0. Inodes are numbered sequentially from 721,
+    /// and hard links carry the data on the last link.
+    pub fn write_spec(&mut self, spec: &str) -> io::Result<()> {
+        for (ino, entry) in (721..).zip(Spec::new(spec)) {
+            let entry = entry?;
+            let metadata = Metadata {
+                ino,
+                ..entry.metadata
+            };
+            if metadata.is_file() {
+                let file = File::open(entry.location)?;
+                let disk = file.metadata()?;
+                let mtime = disk.modified()?.duration_since(UNIX_EPOCH);
+                let metadata = Metadata {
+                    mtime: mtime.map_or(0, |d| u32::try_from(d.as_secs()).unwrap_or(u32::MAX)),
+                    ..metadata
+                };
+                let mut names = Some(entry.name).into_iter().chain(entry.links()).peekable();
+                while let Some(name) = names.next() {
+                    if names.peek().is_some() {
+                        self.write(&metadata, name, &[])?;
+                    } else {
+                        self.write_from(&metadata, name, &file, disk.len())?;
+                    }
+                }
+            } else if metadata.is_symlink() {
+                self.write(&metadata, entry.name, entry.location.as_bytes())?;
+            } else {
+                self.write(&metadata, entry.name, &[])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes the objects yielded by a reader as a [`Spec`] file list.
+///
+/// The locations of files are their paths under `root`, as if the archive
+/// was extracted there. Each hard link set is written as a single `file`
+/// line, and the root directory is written as `.`.
+///
+/// Returns [`ReadError::InvalidName`] or [`ReadError::InvalidSymlink`] if a
+/// name or link target contains whitespace, which the format cannot
+/// represent.
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{dump_spec, CpioReader, CpioStreamWriter};
+///
+/// let spec = "\
+/// dir /dev 0755 0 0
+/// nod /dev/console 0600 0 0 c 5 1
+/// slink /bin/sh busybox 0777 0 0
+/// ";
+/// let mut writer = CpioStreamWriter::new(Vec::new());
+/// writer.write_spec(spec).unwrap();
+/// let buf = writer.finish().unwrap();
+///
+/// assert_eq!(dump_spec(CpioReader::new(&buf).unwrap(), "rootfs").unwrap(), spec);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn dump_spec<'a, I>(objects: I, root: &str) -> Result<String, ReadError>
+where
+    I: IntoIterator<Item = Result<Object<'a>, ReadError>>,
+{
+    let objects = objects.into_iter().collect::<Result<Vec<_>, _>>()?;
+    let links = HardLinks::from_objects(objects.iter().copied().map(Ok))?;
+    let mut spec = String::new();
+    for obj in &objects {
+        let metadata = &obj.metadata;
+        let linked = links.links(metadata);
+        if matches!(linked.first(), Some(first) if first.name != obj.name) {
+            // Already written with the first link.
+            continue;
+        }
+        let name = spec_name(obj.name)?;
+        let mode = metadata.mode & 0o7777;
+        let owner = format!("{:04o} {} {}", mode, metadata.uid, metadata.gid);
+        let file_type = metadata.file_type()?;
+        let line = match file_type {
+            FileType::Regular => {
+                let mut line = format!("file {} {}/{} {}", name, root, normalize(obj.name), owner);
+                for link in linked.iter().skip(1) {
+                    line.push(' ');
+                    line.push_str(&spec_name(link.name)?);
+                }
+                line
+            }
+            FileType::Directory => format!("dir {} {}", name, owner),
+            FileType::Symlink => {
+                let target = obj.symlink_target()?.unwrap_or_default();
+                if target.contains(char::is_whitespace) {
+                    return Err(ReadError::InvalidSymlink);
+                }
+                format!("slink {} {} {}", name, target, owner)
+            }
+            FileType::CharDevice | FileType::BlockDevice => {
+                let dev_type = if file_type == FileType::CharDevice {
+                    'c'
+                } else {
+                    'b'
+                };
+                format!(
+                    "nod {} {} {} {} {}",
+                    name, owner, dev_type, metadata.rdev_major, metadata.rdev_minor
+                )
+            }
+            FileType::Fifo => format!("pipe {} {}", name, owner),
+            FileType::Socket => format!("sock {} {}", name, owner),
+        };
+        spec.push_str(&line);
+        spec.push('\n');
+    }
+    Ok(spec)
+}
+
+/// Returns the name of an object as written in a [`Spec`].
+#[cfg(feature = "alloc")]
+fn spec_name(name: &str) -> Result<String, ReadError> {
+    if name.contains(char::is_whitespace) {
+        return Err(ReadError::InvalidName);
+    }
+    Ok(match &*normalize(name) {
+        "" => ".".into(),
+        name => format!("/{}", name),
+    })
+}
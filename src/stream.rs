@@ -0,0 +1,336 @@
+use std::io::{self, Read, Write};
+
+use crate::writer::{check_name, newc_header};
+use crate::{pad_to_4, Format, Metadata, ReadError, WriteError, NEWC_HEADER_LEN, TRAILER_NAME};
+
+/// The largest accepted name size, including the terminating NUL. This is
+/// `PATH_MAX` on Linux, longer names could not be extracted there anyway.
+const MAX_NAME_SIZE: usize = 4096;
+
+/// A streaming CPIO file reader over [`io::Read`].
+///
+/// Headers are read on demand, so the archive never has to be held in
+/// memory, and no seeking is required. The format is detected from the first
+/// magic number.
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{CpioNewcWriter, CpioStreamReader, Metadata};
+/// use std::io::Read;
+///
+/// let mut buf = [0u8; 256];
+/// let mut writer = CpioNewcWriter::new(&mut buf);
+/// writer.write(&Metadata::default(), "hello.txt", b"Hello").unwrap();
+/// let len = writer.finish().unwrap();
+///
+/// let mut reader = CpioStreamReader::new(&buf[..len]);
+/// while let Some(mut obj) = reader.next_object().unwrap() {
+///     let mut data = Vec::new();
+///     obj.read_to_end(&mut data).unwrap();
+///     assert_eq!(obj.name, "hello.txt");
+///     assert_eq!(data, b"Hello");
+/// }
+/// ```
+pub struct CpioStreamReader<R> {
+    inner: R,
+    format: Option<Format>,
+    /// Unread data bytes of the current object.
+    remaining: u64,
+    /// Padding bytes following the data of the current object.
+    padding: usize,
+    concatenated: bool,
+    /// Index of the archive the last object was read from.
+    segment: usize,
+    /// Number of trailers read so far.
+    trailers: usize,
+    finished: bool,
+}
+
+impl<R: Read> CpioStreamReader<R> {
+    /// Creates a new CPIO reader on the stream.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            format: None,
+            remaining: 0,
+            padding: 0,
+            concatenated: false,
+            segment: 0,
+            trailers: 0,
+            finished: false,
+        }
+    }
+
+    /// Sets whether to continue reading after a trailer, like the Linux
+    /// initramfs loader does.
+    ///
+    /// In this mode, NUL padding between archives is skipped, and the
+    /// iteration ends at the end of the stream. The padding is read one byte
+    /// at a time, so unbuffered streams should be wrapped in a
+    /// [`BufReader`](std::io::BufReader).
+    pub fn concatenated(mut self, concatenated: bool) -> Self {
+        self.concatenated = concatenated;
+        self
+    }
+
+    /// Returns the detected format, or `None` if no header was read yet.
+    pub fn format(&self) -> Option<Format> {
+        self.format
+    }
+
+    /// Returns the index of the archive the last object was read from, or 0
+    /// if no object was read yet.
+    ///
+    /// This is always 0 unless reading [`concatenated`](Self::concatenated)
+    /// archives. Trailers do not change it, so after the end of the stream
+    /// it is the index of the archive holding the last object.
+    pub fn segment(&self) -> usize {
+        self.segment
+    }
+
+    /// Unwraps this reader, returning the underlying stream.
+    ///
+    /// After the trailer was read, the stream is positioned right after it.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads the header of the next object.
+    ///
+    /// The unread data of the previous object is skipped. Returns `None`
+    /// after the trailer, or at the end of the stream when reading
+    /// [`concatenated`](Self::concatenated) archives. Names longer than
+    /// 4095 bytes are rejected with [`ReadError::InvalidName`] before any
+    /// of them is read.
+    pub fn next_object(&mut self) -> io::Result<Option<StreamObject<'_, R>>> {
+        let mut after_trailer = false;
+        loop {
+            if self.finished {
+                return Ok(None);
+            }
+            self.skip(self.remaining + self.padding as u64)?;
+            self.remaining = 0;
+            self.padding = 0;
+
+            let mut header = [0u8; NEWC_HEADER_LEN];
+            let mut filled = 0;
+            if after_trailer {
+                match self.skip_padding()? {
+                    Some(byte) => {
+                        header[0] = byte;
+                        filled = 1;
+                    }
+                    None => {
+                        self.finished = true;
+                        return Ok(None);
+                    }
+                }
+            }
+            while filled < 6 {
+                match self.inner.read(&mut header[filled..6]) {
+                    Ok(0) if filled == 0 => return Err(ReadError::MissingTrailer.into()),
+                    Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    Ok(n) => filled += n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            let format = Format::detect(&header).ok_or(ReadError::InvalidMagic)?;
+            if !format.is_compatible(*self.format.get_or_insert(format)) {
+                return Err(ReadError::InvalidMagic.into());
+            }
+            let header = &mut header[..format.header_len()];
+            self.inner.read_exact(&mut header[6..])?;
+            let (metadata, name_size) = format.parse_header(header)?;
+            if name_size > MAX_NAME_SIZE {
+                return Err(ReadError::InvalidName.into());
+            }
+
+            let mut name = Vec::new();
+            (&mut self.inner)
+                .take(name_size as u64)
+                .read_to_end(&mut name)?;
+            if name.len() != name_size {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            if name.pop() != Some(0) {
+                return Err(ReadError::InvalidName.into());
+            }
+            let name = String::from_utf8(name).map_err(|_| ReadError::InvalidName)?;
+            self.skip(format.name_padding(name_size) as u64)?;
+
+            self.remaining = u64::from(metadata.file_size);
+            self.padding = format.data_padding(metadata.file_size);
+            if name == TRAILER_NAME {
+                self.trailers += 1;
+                self.finished = !self.concatenated;
+                after_trailer = true;
+                continue;
+            }
+            self.segment = self.trailers;
+            return Ok(Some(StreamObject {
+                metadata,
+                name,
+                reader: self,
+            }));
+        }
+    }
+
+    /// Skips NUL padding between archives, returning the first byte after
+    /// it, or `None` at the end of the stream.
+    fn skip_padding(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        loop {
+            match self.inner.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) if byte[0] == 0 => {}
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn skip(&mut self, len: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.inner).take(len), &mut io::sink())?;
+        if skipped != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+/// File system object in a streamed CPIO file.
+///
+/// The file data is read through the [`Read`] implementation.
+pub struct StreamObject<'r, R> {
+    /// The file metadata.
+    pub metadata: Metadata,
+    /// The full pathname.
+    pub name: String,
+    reader: &'r mut CpioStreamReader<R>,
+}
+
+impl<R: Read> Read for StreamObject<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let reader = &mut *self.reader;
+        let len = (buf.len() as u64).min(reader.remaining) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        let n = reader.inner.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        reader.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// A streaming CPIO file (newc format) writer over [`io::Write`].
+///
+/// The output is identical to what [`CpioNewcWriter`] produces.
+///
+/// [`CpioNewcWriter`]: crate::CpioNewcWriter
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{CpioNewcReader, CpioStreamWriter, Metadata};
+///
+/// let metadata = Metadata {
+///     mode: 0o100644,
+///     nlink: 1,
+///     ..Default::default()
+/// };
+/// let mut writer = CpioStreamWriter::new(Vec::new());
+/// writer.write(&metadata, "hello.txt", b"Hello").unwrap();
+/// writer
+///     .write_from(&metadata, "world.txt", &b"World"[..], 5)
+///     .unwrap();
+/// let buf = writer.finish().unwrap();
+///
+/// let names: Vec<_> = CpioNewcReader::new(&buf).map(|obj| obj.unwrap().name).collect();
+/// assert_eq!(names, ["hello.txt", "world.txt"]);
+/// ```
+pub struct CpioStreamWriter<W> {
+    inner: W,
+}
+
+impl<W: Write> CpioStreamWriter<W> {
+    /// Creates a new CPIO writer on the stream.
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Appends an object to the archive.
+    ///
+    /// The `file_size` field of `metadata` is ignored, the length of `data`
+    /// is written instead.
+    pub fn write(&mut self, metadata: &Metadata, name: &str, data: &[u8]) -> io::Result<()> {
+        self.write_from(metadata, name, data, data.len() as u64)
+    }
+
+    /// Appends an object to the archive, copying exactly `len` bytes of data
+    /// from `data`.
+    ///
+    /// The `file_size` field of `metadata` is ignored, `len` is written
+    /// instead. An [`io::ErrorKind::UnexpectedEof`] error is returned if
+    /// `data` ends early, leaving the archive truncated.
+    pub fn write_from(
+        &mut self,
+        metadata: &Metadata,
+        name: &str,
+        data: impl Read,
+        len: u64,
+    ) -> io::Result<()> {
+        check_name(name)?;
+        self.write_object(metadata, name, data, len)
+    }
+
+    /// Writes the trailer and returns the underlying stream.
+    pub fn finish(mut self) -> io::Result<W> {
+        let metadata = Metadata {
+            nlink: 1,
+            ..Default::default()
+        };
+        self.write_object(&metadata, TRAILER_NAME, io::empty(), 0)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_object(
+        &mut self,
+        metadata: &Metadata,
+        name: &str,
+        data: impl Read,
+        len: u64,
+    ) -> io::Result<()> {
+        const ZEROS: [u8; 4] = [0; 4];
+
+        let header = newc_header(metadata, name, len, None)?;
+        let name_end = NEWC_HEADER_LEN + name.len() + 1;
+        self.inner.write_all(&header)?;
+        self.inner.write_all(name.as_bytes())?;
+        self.inner.write_all(&ZEROS[..1 + pad_to_4(name_end)])?;
+        let copied = io::copy(&mut data.take(len), &mut self.inner)?;
+        if copied != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.inner.write_all(&ZEROS[..pad_to_4(len as usize)])?;
+        Ok(())
+    }
+}
+
+impl From<WriteError> for io::Error {
+    fn from(err: WriteError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+impl From<ReadError> for io::Error {
+    fn from(err: ReadError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
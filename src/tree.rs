@@ -0,0 +1,171 @@
+use alloc::borrow::Cow;
+use alloc::collections::{btree_set, BTreeMap, BTreeSet};
+
+use crate::index::normalize;
+use crate::{CpioReader, FileType, Metadata, Object, ReadError};
+
+/// A hierarchical view over the flat list of objects in an archive.
+///
+/// Parent directories missing from the archive are synthesized with mode
+/// `0o755`. If a path appears more than once, the last object wins, as in
+/// the Linux kernel. Paths are normalized the same way as in [`CpioIndex`].
+///
+/// [`CpioIndex`]: crate::CpioIndex
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{CpioNewcWriter, CpioTree, Metadata};
+///
+/// let mut buf = [0u8; 512];
+/// let mut writer = CpioNewcWriter::new(&mut buf);
+/// let metadata = Metadata {
+///     mode: 0o100644,
+///     ..Default::default()
+/// };
+/// writer.write(&metadata, "etc/init.d/rcS", b"#!/bin/sh").unwrap();
+/// writer.write(&metadata, "init", b"#!/bin/sh").unwrap();
+/// let len = writer.finish().unwrap();
+///
+/// let tree = CpioTree::new(&buf[..len]).unwrap();
+/// let names: Vec<_> = tree.read_dir("/").unwrap().map(|e| e.name).collect();
+/// assert_eq!(names, ["etc", "init"]);
+/// assert!(tree.metadata("/etc/init.d").unwrap().is_dir());
+/// ```
+pub struct CpioTree<'a> {
+    nodes: BTreeMap<Cow<'a, str>, Node<'a>>,
+}
+
+#[derive(Default)]
+struct Node<'a> {
+    /// The object, or `None` for a synthesized directory.
+    object: Option<Object<'a>>,
+    /// Normalized paths of the children.
+    children: BTreeSet<Cow<'a, str>>,
+}
+
+impl<'a> CpioTree<'a> {
+    /// Builds the tree from the archive in the buffer.
+    pub fn new(buf: &'a [u8]) -> Result<Self, ReadError> {
+        Self::from_objects(CpioReader::new(buf)?)
+    }
+
+    /// Builds the tree from the objects yielded by a reader.
+    pub fn from_objects<I>(objects: I) -> Result<Self, ReadError>
+    where
+        I: IntoIterator<Item = Result<Object<'a>, ReadError>>,
+    {
+        let mut tree = CpioTree {
+            nodes: BTreeMap::new(),
+        };
+        tree.nodes.insert("".into(), Node::default());
+        for obj in objects {
+            tree.insert(obj?);
+        }
+        Ok(tree)
+    }
+
+    fn insert(&mut self, obj: Object<'a>) {
+        let path = normalize(obj.name);
+        self.nodes.entry(path.clone()).or_default().object = Some(obj);
+        let mut child = path;
+        while !child.is_empty() {
+            let parent = parent(&child);
+            let siblings = &mut self.nodes.entry(parent.clone()).or_default().children;
+            // Once a child is linked, so are all of its ancestors.
+            if !siblings.insert(child) {
+                break;
+            }
+            child = parent;
+        }
+    }
+
+    /// Returns the object at `path`, or `None` if it does not exist or is a
+    /// synthesized directory.
+    pub fn get(&self, path: &str) -> Option<Object<'a>> {
+        self.nodes.get(&*normalize(path))?.object
+    }
+
+    /// Returns the metadata of the object at `path`.
+    pub fn metadata(&self, path: &str) -> Option<Metadata> {
+        let node = self.nodes.get(&*normalize(path))?;
+        Some(node.metadata())
+    }
+
+    /// Returns the target of the symbolic link at `path`, or `None` if it
+    /// does not exist or is not a symbolic link.
+    pub fn read_link(&self, path: &str) -> Result<Option<&'a str>, ReadError> {
+        match self.get(path) {
+            Some(obj) => obj.symlink_target(),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the children of the directory at `path`, sorted by name.
+    ///
+    /// Returns `None` if `path` does not exist or is not a directory.
+    pub fn read_dir(&self, path: &str) -> Option<ReadDir<'_, 'a>> {
+        let node = self.nodes.get(&*normalize(path))?;
+        if !node.metadata().is_dir() {
+            return None;
+        }
+        Some(ReadDir {
+            tree: self,
+            children: node.children.iter(),
+        })
+    }
+}
+
+/// Returns the parent of a normalized path, borrowing from the archive if
+/// the path does.
+fn parent<'a>(path: &Cow<'a, str>) -> Cow<'a, str> {
+    let end = path.rfind('/').unwrap_or(0);
+    match path {
+        Cow::Borrowed(path) => Cow::Borrowed(&path[..end]),
+        Cow::Owned(path) => Cow::Owned(path[..end].into()),
+    }
+}
+
+impl Node<'_> {
+    fn metadata(&self) -> Metadata {
+        match &self.object {
+            Some(obj) => obj.metadata,
+            None => Metadata {
+                mode: FileType::Directory.mode_bits() | 0o755,
+                nlink: 2,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Iterator over the children of a directory in a [`CpioTree`].
+pub struct ReadDir<'t, 'a> {
+    tree: &'t CpioTree<'a>,
+    children: btree_set::Iter<'t, Cow<'a, str>>,
+}
+
+impl<'t> Iterator for ReadDir<'t, '_> {
+    type Item = DirEntry<'t>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let path = &**self.children.next()?;
+        let name = path.rfind('/').map_or(path, |i| &path[i + 1..]);
+        Some(DirEntry {
+            name,
+            path,
+            metadata: self.tree.nodes[path].metadata(),
+        })
+    }
+}
+
+/// An entry returned by [`ReadDir`], borrowed from the [`CpioTree`].
+#[derive(Debug, Clone, Copy)]
+pub struct DirEntry<'t> {
+    /// The file name.
+    pub name: &'t str,
+    /// The normalized full path.
+    pub path: &'t str,
+    /// The file metadata.
+    pub metadata: Metadata,
+}
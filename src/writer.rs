@@ -0,0 +1,199 @@
+use core::convert::TryFrom;
+use core::fmt;
+
+use crate::{checksum, pad_to_4, Metadata, CRC_MAGIC, NEWC_HEADER_LEN, NEWC_MAGIC, TRAILER_NAME};
+
+/// A CPIO file (newc format) writer.
+///
+/// Objects are serialized into a caller-supplied buffer, so no allocator is
+/// required. The output can be read back by [`CpioNewcReader`].
+///
+/// [`CpioNewcReader`]: crate::CpioNewcReader
+///
+/// # Example
+///
+/// ```rust
+/// use cpio::{CpioNewcReader, CpioNewcWriter, Metadata};
+///
+/// let mut buf = [0u8; 512];
+/// let mut writer = CpioNewcWriter::new(&mut buf);
+/// let metadata = Metadata {
+///     mode: 0o100644,
+///     nlink: 1,
+///     ..Default::default()
+/// };
+/// writer.write(&metadata, "hello.txt", b"Hello, world!\n").unwrap();
+/// let len = writer.finish().unwrap();
+///
+/// let obj = CpioNewcReader::new(&buf[..len]).next().unwrap().unwrap();
+/// assert_eq!(obj.name, "hello.txt");
+/// assert_eq!(obj.data, b"Hello, world!\n");
+/// ```
+pub struct CpioNewcWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+    crc: bool,
+}
+
+impl<'a> CpioNewcWriter<'a> {
+    /// Creates a new CPIO writer on the buffer.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            crc: false,
+        }
+    }
+
+    /// Creates a new CPIO writer on the buffer, using the newc-with-CRC
+    /// format (magic `070702`).
+    ///
+    /// The checksum of regular files is computed from their data, the
+    /// `check` field of [`Metadata`] is ignored.
+    pub fn new_crc(buf: &'a mut [u8]) -> Self {
+        Self {
+            crc: true,
+            ..Self::new(buf)
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Appends an object to the archive.
+    ///
+    /// The `file_size` field of `metadata` is ignored, the length of `data`
+    /// is written instead. Nothing is written if an error is returned.
+    pub fn write(
+        &mut self,
+        metadata: &Metadata,
+        name: &str,
+        data: &[u8],
+    ) -> Result<(), WriteError> {
+        check_name(name)?;
+        self.write_object(metadata, name, data)
+    }
+
+    /// Writes the trailer and returns the total length of the archive.
+    pub fn finish(mut self) -> Result<usize, WriteError> {
+        let metadata = Metadata {
+            nlink: 1,
+            ..Default::default()
+        };
+        self.write_object(&metadata, TRAILER_NAME, &[])?;
+        Ok(self.pos)
+    }
+
+    fn write_object(
+        &mut self,
+        metadata: &Metadata,
+        name: &str,
+        data: &[u8],
+    ) -> Result<(), WriteError> {
+        let check = if self.crc {
+            Some(if metadata.is_file() {
+                checksum(data)
+            } else {
+                0
+            })
+        } else {
+            None
+        };
+        let header = newc_header(metadata, name, data.len() as u64, check)?;
+        let name_end = NEWC_HEADER_LEN + name.len() + 1;
+        let data_start = name_end + pad_to_4(name_end);
+        let data_end = data_start + data.len();
+        let total = data_end + pad_to_4(data.len());
+        if self.buf.len() - self.pos < total {
+            return Err(WriteError::BufTooShort);
+        }
+
+        let out = &mut self.buf[self.pos..self.pos + total];
+        out[..NEWC_HEADER_LEN].copy_from_slice(&header);
+        out[NEWC_HEADER_LEN..name_end - 1].copy_from_slice(name.as_bytes());
+        out[name_end - 1..data_start].fill(0);
+        out[data_start..data_end].copy_from_slice(data);
+        out[data_end..].fill(0);
+        self.pos += total;
+        Ok(())
+    }
+}
+
+/// Checks that `name` can be stored as the name of a regular object.
+pub(crate) fn check_name(name: &str) -> Result<(), WriteError> {
+    if name.as_bytes().contains(&0) || name == TRAILER_NAME {
+        return Err(WriteError::InvalidName);
+    }
+    Ok(())
+}
+
+/// Encodes a newc header for an object with the given name and data length.
+///
+/// The newc-with-CRC magic is used if `check` is given.
+pub(crate) fn newc_header(
+    metadata: &Metadata,
+    name: &str,
+    data_len: u64,
+    check: Option<u32>,
+) -> Result<[u8; NEWC_HEADER_LEN], WriteError> {
+    let file_size = u32::try_from(data_len).map_err(|_| WriteError::DataTooLong)?;
+    let name_size = u32::try_from(name.len() + 1).map_err(|_| WriteError::InvalidName)?;
+
+    let mut header = [0u8; NEWC_HEADER_LEN];
+    let magic = if check.is_some() {
+        CRC_MAGIC
+    } else {
+        NEWC_MAGIC
+    };
+    header[..6].copy_from_slice(magic);
+    let fields = [
+        metadata.ino,
+        metadata.mode,
+        metadata.uid,
+        metadata.gid,
+        metadata.nlink,
+        metadata.mtime,
+        file_size,
+        metadata.dev_major,
+        metadata.dev_minor,
+        metadata.rdev_major,
+        metadata.rdev_minor,
+        name_size,
+        check.unwrap_or(0),
+    ];
+    for (chunk, value) in header[6..].chunks_exact_mut(8).zip(fields.iter()) {
+        write_hex_u32(chunk, *value);
+    }
+    Ok(header)
+}
+
+/// Writes `value` as 8 uppercase hexadecimal digits.
+fn write_hex_u32(out: &mut [u8], value: u32) {
+    const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = DIGITS[(value >> ((7 - i) * 4)) as usize & 0xf];
+    }
+}
+
+/// The error type which is returned from CPIO writer.
+#[derive(Debug, PartialEq, Eq)]
+pub enum WriteError {
+    InvalidName,
+    DataTooLong,
+    BufTooShort,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::InvalidName => write!(f, "invalid file name"),
+            WriteError::DataTooLong => write!(f, "file data too long"),
+            WriteError::BufTooShort => write!(f, "buffer too short"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WriteError {}
@@ -0,0 +1,250 @@
+#![cfg(all(
+    feature = "std",
+    any(feature = "gzip", feature = "zstd", feature = "xz", feature = "lz4")
+))]
+
+use std::io::{self, Read, Write};
+
+use cpio::{
+    Compression, Compressor, CpioReader, CpioStreamReader, CpioStreamWriter, Decompressor,
+    Metadata, ReadError,
+};
+
+const FILE: Metadata = Metadata {
+    ino: 1,
+    mode: 0o100644,
+    uid: 0,
+    gid: 0,
+    nlink: 1,
+    mtime: 0,
+    file_size: 0,
+    dev_major: 0,
+    dev_minor: 0,
+    rdev_major: 0,
+    rdev_minor: 0,
+    check: 0,
+};
+
+/// Writes a newc archive holding files with the given names and data.
+fn newc(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = CpioStreamWriter::new(Vec::new());
+    for (name, data) in files {
+        writer.write(&FILE, name, data).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+/// Every compression format, its magic number, and whether its feature is
+/// enabled.
+const FORMATS: &[(Compression, &[u8], bool)] = &[
+    (Compression::Gzip, &[0x1f, 0x8b], cfg!(feature = "gzip")),
+    (
+        Compression::Zstd,
+        &[0x28, 0xb5, 0x2f, 0xfd],
+        cfg!(feature = "zstd"),
+    ),
+    (
+        Compression::Xz,
+        &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00],
+        cfg!(feature = "xz"),
+    ),
+    (Compression::Lzma, &[0x5d, 0x00, 0x00], cfg!(feature = "xz")),
+    (
+        Compression::Lz4Legacy,
+        &[0x02, 0x21, 0x4c, 0x18],
+        cfg!(feature = "lz4"),
+    ),
+];
+
+/// Returns the compression formats whose features are enabled.
+fn enabled() -> impl Iterator<Item = Compression> {
+    FORMATS
+        .iter()
+        .filter(|&&(_, _, enabled)| enabled)
+        .map(|&(compression, _, _)| compression)
+}
+
+/// Compresses the data with the default level of the format.
+fn compress(compression: Compression, data: &[u8]) -> Vec<u8> {
+    let mut compressor = Compressor::new(Vec::new(), compression).unwrap();
+    compressor.write_all(data).unwrap();
+    compressor.finish().unwrap()
+}
+
+/// Returns the names of the objects of the concatenated archives.
+fn names(buf: &[u8]) -> Vec<&str> {
+    CpioReader::new(buf)
+        .unwrap()
+        .concatenated(true)
+        .map(|obj| obj.unwrap().name)
+        .collect()
+}
+
+/// An uncompressed early cpio followed by a compressed archive, and the
+/// concatenation of both archives.
+fn image(compression: Compression) -> (Vec<u8>, Vec<u8>) {
+    let early = newc(&[("kernel/x86/microcode/GenuineIntel.bin", b"ucode")]);
+    let main = newc(&[("init", b"#!/bin/sh\n"), ("bin/sh", &[0x5a; 1000])]);
+    let mut image = early.clone();
+    image.extend_from_slice(&[0; 512]);
+    image.extend(compress(compression, &main));
+    image.extend_from_slice(&[0; 16]);
+    ([early, main.clone()].concat(), image)
+}
+
+/// Decompresses the image through [`Decompressor`].
+fn decompress_stream(image: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    Decompressor::new(image)?.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// Returns the `ReadError` wrapped in an I/O error.
+fn read_error(err: io::Error) -> ReadError {
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    *err.into_inner().unwrap().downcast().unwrap()
+}
+
+#[test]
+fn after_early_cpio() {
+    for compression in enabled() {
+        let (expected, image) = image(compression);
+        assert_eq!(&*cpio::decompress(&image).unwrap(), &expected[..]);
+
+        let mut out = vec![0; expected.len()];
+        let buf = cpio::decompress_into(&image, &mut out).unwrap();
+        assert_eq!(buf, &expected[..], "{:?}", compression);
+        let mut out = vec![0; expected.len() - 1];
+        assert_eq!(
+            cpio::decompress_into(&image, &mut out).unwrap_err(),
+            ReadError::OutputTooShort,
+            "{:?}",
+            compression
+        );
+
+        assert_eq!(decompress_stream(&image).unwrap(), expected);
+        let decompressor = Decompressor::new(&image[..]).unwrap();
+        assert_eq!(decompressor.compression(), None);
+        let mut reader = CpioStreamReader::new(decompressor).concatenated(true);
+        let mut names = Vec::new();
+        while let Some(mut obj) = reader.next_object().unwrap() {
+            io::copy(&mut obj, &mut io::sink()).unwrap();
+            names.push((obj.name, reader.segment()));
+        }
+        assert_eq!(
+            names,
+            [
+                ("kernel/x86/microcode/GenuineIntel.bin".into(), 0),
+                ("init".into(), 1),
+                ("bin/sh".into(), 1),
+            ]
+        );
+    }
+}
+
+#[test]
+fn several_compressed_archives() {
+    let mut image = Vec::new();
+    let mut expected = Vec::new();
+    for compression in enabled() {
+        let archive = newc(&[(&format!("{:?}", compression), b"data")]);
+        image.extend(compress(compression, &archive));
+        expected.extend(archive);
+    }
+    let names = names(&expected);
+    assert_eq!(names.len(), enabled().count());
+
+    assert_eq!(&*cpio::decompress(&image).unwrap(), &expected[..]);
+    let mut out = vec![0; expected.len()];
+    assert_eq!(
+        cpio::decompress_into(&image, &mut out).unwrap(),
+        &expected[..]
+    );
+    assert_eq!(decompress_stream(&image).unwrap(), expected);
+    let decompressor = Decompressor::new(&image[..]).unwrap();
+    assert_eq!(decompressor.compression(), enabled().next());
+}
+
+#[test]
+fn unsupported_compression() {
+    for &(compression, magic, enabled) in FORMATS {
+        assert_eq!(Compression::detect(magic), Some(compression));
+        if enabled {
+            continue;
+        }
+        let mut image = newc(&[("early", b"")]);
+        image.extend_from_slice(magic);
+        image.extend_from_slice(&[0; 32]);
+        assert_eq!(
+            cpio::decompress(&image).unwrap_err(),
+            ReadError::UnsupportedCompression
+        );
+        let mut out = vec![0; 4096];
+        assert_eq!(
+            cpio::decompress_into(&image, &mut out).unwrap_err(),
+            ReadError::UnsupportedCompression
+        );
+        let err = decompress_stream(&image).unwrap_err();
+        assert_eq!(read_error(err), ReadError::UnsupportedCompression);
+    }
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_checksum_mismatch() {
+    let archive = newc(&[("init", b"#!/bin/sh\n")]);
+    let mut image = compress(Compression::Gzip, &archive);
+    // The CRC-32 is the first half of the trailer.
+    let crc = image.len() - 8;
+    image[crc] ^= 1;
+    assert_eq!(
+        cpio::decompress(&image).unwrap_err(),
+        ReadError::ChecksumMismatch
+    );
+    let mut out = vec![0; 4096];
+    assert_eq!(
+        cpio::decompress_into(&image, &mut out).unwrap_err(),
+        ReadError::ChecksumMismatch
+    );
+}
+
+#[test]
+fn compressor_levels() {
+    let archive = newc(&[("init", b"#!/bin/sh\n"), ("bin/sh", &[0x5a; 100_000])]);
+    for compression in enabled() {
+        for &level in &[0, 1, 9, 100] {
+            let mut compressor = Compressor::with_level(Vec::new(), compression, level).unwrap();
+            assert_eq!(compressor.compression(), compression);
+            // Small writes are buffered like large ones.
+            for chunk in archive.chunks(1000) {
+                compressor.write_all(chunk).unwrap();
+            }
+            let image = compressor.finish().unwrap();
+            assert_eq!(Compression::detect(&image), Some(compression));
+
+            let buf = cpio::decompress(&image).unwrap();
+            assert_eq!(&*buf, &archive[..], "{:?} level {}", compression, level);
+            assert_eq!(names(&buf), ["init", "bin/sh"]);
+            assert_eq!(decompress_stream(&image).unwrap(), archive);
+        }
+    }
+}
+
+#[test]
+fn compressor_empty() {
+    for compression in enabled() {
+        let image = compress(compression, b"");
+        assert!(cpio::decompress(&image).unwrap().is_empty());
+        assert!(decompress_stream(&image).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn compressor_unsupported() {
+    for &(compression, _, enabled) in FORMATS {
+        if !enabled {
+            let err = Compressor::new(Vec::new(), compression).err().unwrap();
+            assert_eq!(read_error(err), ReadError::UnsupportedCompression);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+#![cfg(all(feature = "std", unix))]
+
+use std::fs;
+use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+use std::path::Path;
+
+use cpio::{Archiver, CpioNewcReader, Extractor, Mtime};
+
+mod common;
+
+use common::TempDir;
+
+/// Creates a small root file system at `root`.
+fn create_tree(root: &Path) {
+    fs::create_dir_all(root.join("bin")).unwrap();
+    fs::create_dir_all(root.join("etc/init.d")).unwrap();
+    fs::write(root.join("bin/busybox"), b"\x7fELF").unwrap();
+    fs::set_permissions(root.join("bin/busybox"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::hard_link(root.join("bin/busybox"), root.join("bin/sh")).unwrap();
+    fs::write(root.join("etc/init.d/rcS"), b"#!/bin/sh\n").unwrap();
+    symlink("bin/busybox", root.join("init")).unwrap();
+}
+
+#[test]
+fn archive_tree() {
+    let tmp = TempDir::new("archive_tree");
+    let src = tmp.join("src");
+    create_tree(&src);
+    let buf = Archiver::new(&src).write(Vec::new()).unwrap();
+
+    let objects: Vec<_> = CpioNewcReader::new(&buf).map(|obj| obj.unwrap()).collect();
+    let names: Vec<_> = objects.iter().map(|obj| obj.name).collect();
+    assert_eq!(
+        names,
+        [
+            ".",
+            "bin",
+            "bin/busybox",
+            "bin/sh",
+            "etc",
+            "etc/init.d",
+            "etc/init.d/rcS",
+            "init"
+        ]
+    );
+    // The data of hard links is stored on the last link only.
+    assert_eq!(objects[2].data, b"");
+    assert_eq!(objects[3].data, b"\x7fELF");
+    assert_eq!(objects[2].metadata.ino, objects[3].metadata.ino);
+    assert_eq!(objects[2].metadata.nlink, 2);
+    assert_eq!(objects[3].metadata.mode, 0o100755);
+    assert_eq!(objects[6].data, b"#!/bin/sh\n");
+    assert_eq!(objects[7].symlink_target().unwrap(), Some("bin/busybox"));
+    assert!(objects[1].metadata.is_dir());
+}
+
+#[test]
+fn archive_and_extract() {
+    let tmp = TempDir::new("archive_and_extract");
+    let src = tmp.join("src");
+    let dest = tmp.join("dest");
+    create_tree(&src);
+    let buf = Archiver::new(&src).write(Vec::new()).unwrap();
+    Extractor::new(&dest)
+        .extract(CpioNewcReader::new(&buf))
+        .unwrap();
+
+    for name in ["bin/busybox", "bin/sh", "etc/init.d/rcS"] {
+        assert_eq!(
+            fs::read(src.join(name)).unwrap(),
+            fs::read(dest.join(name)).unwrap()
+        );
+    }
+    let busybox = fs::metadata(dest.join("bin/busybox")).unwrap();
+    assert_eq!(
+        busybox.ino(),
+        fs::metadata(dest.join("bin/sh")).unwrap().ino()
+    );
+    assert_eq!(busybox.mode() & 0o7777, 0o755);
+    assert_eq!(
+        busybox.mtime(),
+        fs::metadata(src.join("bin/busybox")).unwrap().mtime()
+    );
+    assert_eq!(
+        fs::read_link(dest.join("init")).unwrap(),
+        Path::new("bin/busybox")
+    );
+}
+
+/// Archives the tree with all normalizing options enabled.
+fn archive_reproducibly(src: &Path) -> Vec<u8> {
+    Archiver::new(src)
+        .mtime(Mtime::Set(1_000_000))
+        .renumber_inodes(true)
+        .root_owned(true)
+        .zero_dev(true)
+        .write(Vec::new())
+        .unwrap()
+}
+
+#[test]
+fn reproducible() {
+    let tmp = TempDir::new("reproducible");
+    let first = tmp.join("first");
+    let second = tmp.join("second");
+    create_tree(&first);
+    // Different inode numbers, and a link outside the tree.
+    fs::write(tmp.join("padding"), b"").unwrap();
+    create_tree(&second);
+    fs::hard_link(second.join("etc/init.d/rcS"), tmp.join("rcS")).unwrap();
+
+    let buf = archive_reproducibly(&first);
+    assert_eq!(buf, archive_reproducibly(&second));
+    assert_ne!(
+        Archiver::new(&first).write(Vec::new()).unwrap(),
+        Archiver::new(&second).write(Vec::new()).unwrap()
+    );
+
+    let nlinks: Vec<_> = CpioNewcReader::new(&buf)
+        .map(|obj| {
+            let obj = obj.unwrap();
+            (obj.name, obj.metadata.ino, obj.metadata.nlink)
+        })
+        .collect();
+    assert_eq!(
+        nlinks,
+        [
+            (".", 1, 4),
+            ("bin", 2, 2),
+            ("bin/busybox", 3, 2),
+            ("bin/sh", 3, 2),
+            ("etc", 4, 3),
+            ("etc/init.d", 5, 2),
+            ("etc/init.d/rcS", 6, 1),
+            ("init", 7, 1),
+        ]
+    );
+}
@@ -0,0 +1,102 @@
+use cpio::{find_cpio_data, CpioNewcWriter, Metadata};
+
+const FILE: Metadata = Metadata {
+    ino: 1,
+    mode: 0o100644,
+    uid: 0,
+    gid: 0,
+    nlink: 1,
+    mtime: 0,
+    file_size: 0,
+    dev_major: 0,
+    dev_minor: 0,
+    rdev_major: 0,
+    rdev_minor: 0,
+    check: 0,
+};
+
+const UCODE: &str = "kernel/x86/microcode/";
+
+/// Appends a newc archive holding files with the given names and data to
+/// the buffer at `pos`, returning the end of the archive.
+fn newc(buf: &mut [u8], pos: usize, files: &[(&str, &[u8])]) -> usize {
+    let mut writer = CpioNewcWriter::new(&mut buf[pos..]);
+    for (name, data) in files {
+        writer.write(&FILE, name, data).unwrap();
+    }
+    pos + writer.finish().unwrap()
+}
+
+/// Returns the names and data of the files found under `path`.
+fn found<'a>(path: &str, buf: &'a [u8]) -> Vec<(&'a str, &'a [u8])> {
+    find_cpio_data(path, buf)
+        .map(|found| {
+            assert_eq!(
+                &buf[found.data_offset..][..found.object.data.len()],
+                found.object.data
+            );
+            (found.object.name, found.object.data)
+        })
+        .collect()
+}
+
+#[test]
+fn junk_prefix() {
+    let mut buf = [0u8; 1024];
+    buf[..8].copy_from_slice(b"junkjunk");
+    // A magic number which does not start a valid header.
+    buf[8..16].copy_from_slice(b"070701zz");
+    let end = newc(
+        &mut buf,
+        16,
+        &[(&format!("{}GenuineIntel.bin", UCODE), b"ucode")],
+    );
+
+    let mut iter = find_cpio_data(UCODE, &buf[..end]);
+    let first = iter.next().unwrap();
+    assert_eq!(first.offset, 16);
+    assert_eq!(first.object.data, b"ucode");
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn concatenated_archives() {
+    let mut buf = [0u8; 2048];
+    let pos = newc(
+        &mut buf,
+        0,
+        &[(&format!("{}AuthenticAMD.bin", UCODE), b"amd")],
+    );
+    let pos = newc(&mut buf, pos + 512, &[("init", b"#!/bin/sh")]);
+    let end = newc(
+        &mut buf,
+        pos + 4,
+        &[(&format!("{}GenuineIntel.bin", UCODE), b"intel")],
+    );
+
+    assert_eq!(
+        found(UCODE, &buf[..end]),
+        [
+            ("kernel/x86/microcode/AuthenticAMD.bin", &b"amd"[..]),
+            ("kernel/x86/microcode/GenuineIntel.bin", &b"intel"[..]),
+        ]
+    );
+    assert_eq!(found("", &buf[..end]).len(), 3);
+
+    // A compressed archive ends the search.
+    buf[pos..pos + 4].copy_from_slice(&[0x1f, 0x8b, 8, 0]);
+    assert_eq!(found(UCODE, &buf[..end]).len(), 1);
+}
+
+#[test]
+fn no_match() {
+    assert!(find_cpio_data(UCODE, &[]).next().is_none());
+    assert!(find_cpio_data(UCODE, b"070701").next().is_none());
+    assert!(find_cpio_data(UCODE, &[0x1f; 64]).next().is_none());
+
+    let mut buf = [0u8; 512];
+    let end = newc(&mut buf, 0, &[("init", b"#!/bin/sh")]);
+    let mut iter = find_cpio_data(UCODE, &buf[..end]);
+    assert!(iter.next().is_none());
+    assert!(iter.next().is_none());
+}
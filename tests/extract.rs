@@ -0,0 +1,497 @@
+#![cfg(all(feature = "std", unix))]
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use cpio::{CpioReader, CpioStreamWriter, Extractor, Metadata, Overwrite};
+use cpio::{UnsafePathError, UnsafePathReason};
+
+mod common;
+
+use common::TempDir;
+
+/// Creates a temporary directory holding the destination and a directory
+/// outside of it.
+fn temp_dir(name: &str) -> TempDir {
+    let tmp = TempDir::new(name);
+    fs::create_dir(tmp.join("dest")).unwrap();
+    fs::create_dir(tmp.join("outside")).unwrap();
+    tmp
+}
+
+fn metadata(mode: u32, ino: u32, nlink: u32) -> Metadata {
+    Metadata {
+        ino,
+        mode,
+        nlink,
+        ..Default::default()
+    }
+}
+
+fn file() -> Metadata {
+    metadata(0o100644, 0, 1)
+}
+
+fn symlink() -> Metadata {
+    metadata(0o120777, 0, 1)
+}
+
+/// Writes an archive of the objects, given as metadata, name and data.
+fn archive(objects: &[(Metadata, &str, &[u8])]) -> Vec<u8> {
+    let mut writer = CpioStreamWriter::new(Vec::new());
+    for (metadata, name, data) in objects {
+        writer.write(metadata, name, data).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+fn extract(extractor: Extractor, buf: &[u8]) -> io::Result<()> {
+    extractor.extract(CpioReader::new(buf).unwrap())
+}
+
+fn unsafe_path(err: io::Error) -> UnsafePathError {
+    err.get_ref()
+        .and_then(|err| err.downcast_ref::<UnsafePathError>())
+        .unwrap_or_else(|| panic!("unexpected error: {}", err))
+        .clone()
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+/// Returns the names in the directory, sorted.
+fn list(dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn parent_dir() {
+    for name in ["../x", "a/../../x", "./../outside/x"] {
+        let tmp = temp_dir("parent_dir");
+        let buf = archive(&[(file(), name, b"pwned")]);
+        let err = extract(Extractor::new(tmp.join("dest")), &buf).unwrap_err();
+        let expected = UnsafePathError {
+            name: name.into(),
+            reason: UnsafePathReason::ParentDir,
+        };
+        assert_eq!(unsafe_path(err), expected);
+        assert!(list(&tmp).iter().all(|name| name != "x"));
+        assert!(list(&tmp.join("outside")).is_empty());
+        assert!(list(&tmp.join("dest")).is_empty());
+    }
+}
+
+#[test]
+fn absolute_path() {
+    let tmp = temp_dir("absolute_path");
+    let victim = tmp.join("outside").join("victim");
+    let buf = archive(&[(file(), path_str(&victim), b"pwned")]);
+    extract(Extractor::new(tmp.join("dest")), &buf).unwrap();
+    assert!(list(&tmp.join("outside")).is_empty());
+    let inside = tmp.join("dest").join(victim.strip_prefix("/").unwrap());
+    assert_eq!(fs::read(inside).unwrap(), b"pwned");
+}
+
+#[test]
+fn write_through_symlink_dir() {
+    let tmp = temp_dir("write_through_symlink_dir");
+    let buf = archive(&[
+        (symlink(), "l", path_str(&tmp.join("outside")).as_bytes()),
+        (file(), "l/x", b"pwned"),
+    ]);
+    let err = extract(Extractor::new(tmp.join("dest")), &buf).unwrap_err();
+    let expected = UnsafePathError {
+        name: "l/x".into(),
+        reason: UnsafePathReason::Symlink,
+    };
+    assert_eq!(unsafe_path(err), expected);
+    assert!(list(&tmp.join("outside")).is_empty());
+}
+
+#[test]
+fn write_over_symlink() {
+    let tmp = temp_dir("write_over_symlink");
+    let victim = tmp.join("outside").join("victim");
+    fs::write(&victim, b"safe").unwrap();
+    let buf = archive(&[
+        (symlink(), "f", path_str(&victim).as_bytes()),
+        (file(), "f", b"pwned"),
+    ]);
+    extract(
+        Extractor::new(tmp.join("dest")).overwrite(Overwrite::Always),
+        &buf,
+    )
+    .unwrap();
+    assert_eq!(fs::read(&victim).unwrap(), b"safe");
+    assert_eq!(fs::read(tmp.join("dest").join("f")).unwrap(), b"pwned");
+    assert!(fs::symlink_metadata(tmp.join("dest").join("f"))
+        .unwrap()
+        .is_file());
+}
+
+#[test]
+fn hard_link_to_symlink() {
+    let tmp = temp_dir("hard_link_to_symlink");
+    let victim = tmp.join("outside").join("victim");
+    fs::write(&victim, b"safe").unwrap();
+    let buf = archive(&[
+        (metadata(0o120777, 7, 2), "x", path_str(&victim).as_bytes()),
+        (metadata(0o100644, 7, 2), "y", b"pwned"),
+    ]);
+    let err = extract(Extractor::new(tmp.join("dest")), &buf).unwrap_err();
+    let expected = UnsafePathError {
+        name: "y".into(),
+        reason: UnsafePathReason::HardLink,
+    };
+    assert_eq!(unsafe_path(err), expected);
+    assert_eq!(fs::read(&victim).unwrap(), b"safe");
+    assert!(!tmp.join("dest").join("y").exists());
+}
+
+#[test]
+fn hard_link_to_replaced_file() {
+    let tmp = temp_dir("hard_link_to_replaced_file");
+    let victim = tmp.join("outside").join("victim");
+    fs::write(&victim, b"safe").unwrap();
+    // The first link is replaced by a symbolic link before the last link,
+    // which carries the data, is extracted.
+    let buf = archive(&[
+        (metadata(0o100644, 7, 2), "x", b""),
+        (metadata(0o120777, 8, 1), "x", path_str(&victim).as_bytes()),
+        (metadata(0o100644, 7, 2), "y", b"pwned"),
+    ]);
+    let extractor = Extractor::new(tmp.join("dest")).overwrite(Overwrite::Always);
+    let err = extract(extractor, &buf).unwrap_err();
+    let expected = UnsafePathError {
+        name: "y".into(),
+        reason: UnsafePathReason::HardLink,
+    };
+    assert_eq!(unsafe_path(err), expected);
+    assert_eq!(fs::read(&victim).unwrap(), b"safe");
+}
+
+#[test]
+fn dir_replaced_by_symlink() {
+    let tmp = temp_dir("dir_replaced_by_symlink");
+    fs::set_permissions(tmp.join("outside"), fs::Permissions::from_mode(0o700)).unwrap();
+    let buf = archive(&[
+        (metadata(0o40777, 0, 2), "a", b""),
+        (symlink(), "a", path_str(&tmp.join("outside")).as_bytes()),
+    ]);
+    extract(
+        Extractor::new(tmp.join("dest")).overwrite(Overwrite::Always),
+        &buf,
+    )
+    .unwrap();
+    let mode = fs::metadata(tmp.join("outside"))
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o7777, 0o700);
+    let a = fs::symlink_metadata(tmp.join("dest").join("a")).unwrap();
+    assert!(a.file_type().is_symlink());
+}
+
+/// A modification time later than that of any existing file.
+const LATER: u32 = 4_000_000_000;
+
+fn char_device() -> Metadata {
+    Metadata {
+        rdev_major: 1,
+        rdev_minor: 3,
+        mtime: LATER,
+        ..metadata(0o20644, 0, 1)
+    }
+}
+
+#[test]
+fn hard_link_to_emptied_dir() {
+    let tmp = temp_dir("hard_link_to_emptied_dir");
+    let victim = tmp.join("outside").join("f");
+    fs::write(&victim, b"safe").unwrap();
+    // Unprivileged, the device node cannot be created, which must not leave
+    // `d` empty to be replaced by a symbolic link.
+    let later_symlink = Metadata {
+        mtime: LATER,
+        ..symlink()
+    };
+    let buf = archive(&[
+        (metadata(0o100644, 7, 2), "d/f", b""),
+        (char_device(), "d/f", b""),
+        (
+            later_symlink,
+            "d",
+            path_str(&tmp.join("outside")).as_bytes(),
+        ),
+        (metadata(0o100644, 7, 2), "x", b"pwned"),
+    ]);
+    let err = extract(Extractor::new(tmp.join("dest")), &buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::DirectoryNotEmpty);
+    assert_eq!(fs::read(&victim).unwrap(), b"safe");
+    assert_eq!(list(&tmp.join("dest")), ["d"]);
+    assert_eq!(list(&tmp.join("dest").join("d")), ["f"]);
+}
+
+#[test]
+fn dir_metadata_in_emptied_dir() {
+    let tmp = temp_dir("dir_metadata_in_emptied_dir");
+    let victim = tmp.join("outside").join("b");
+    fs::create_dir(&victim).unwrap();
+    fs::set_permissions(&victim, fs::Permissions::from_mode(0o700)).unwrap();
+    let later_symlink = Metadata {
+        mtime: LATER,
+        ..symlink()
+    };
+    let buf = archive(&[
+        (metadata(0o40777, 0, 2), "a/b", b""),
+        (char_device(), "a/b", b""),
+        (
+            later_symlink,
+            "a",
+            path_str(&tmp.join("outside")).as_bytes(),
+        ),
+    ]);
+    let err = extract(Extractor::new(tmp.join("dest")), &buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::DirectoryNotEmpty);
+    let mode = fs::metadata(&victim).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o700);
+    assert_eq!(list(&tmp.join("dest")), ["a"]);
+}
+
+/// Extracts the archive, calling `modify` before the object at `index` is
+/// read, like a concurrent process modifying the destination.
+fn extract_modified(
+    extractor: Extractor,
+    buf: &[u8],
+    index: usize,
+    mut modify: impl FnMut(),
+) -> io::Result<()> {
+    let objects = CpioReader::new(buf).unwrap().enumerate().map(|(i, obj)| {
+        if i == index {
+            modify();
+        }
+        obj
+    });
+    extractor.extract(objects)
+}
+
+/// Replaces `dir` by a symbolic link to `target`.
+fn replace_by_symlink(dir: &Path, target: &Path) {
+    fs::rename(dir, dir.with_extension("moved")).unwrap();
+    std::os::unix::fs::symlink(target, dir).unwrap();
+}
+
+#[test]
+fn hard_link_below_replaced_dir() {
+    let tmp = temp_dir("hard_link_below_replaced_dir");
+    let victim = tmp.join("outside").join("f");
+    fs::write(&victim, b"safe").unwrap();
+    let buf = archive(&[
+        (metadata(0o100644, 7, 2), "d/f", b""),
+        (metadata(0o100644, 7, 2), "x", b"pwned"),
+    ]);
+    let err = extract_modified(Extractor::new(tmp.join("dest")), &buf, 1, || {
+        replace_by_symlink(&tmp.join("dest").join("d"), &tmp.join("outside"))
+    })
+    .unwrap_err();
+    let expected = UnsafePathError {
+        name: "x".into(),
+        reason: UnsafePathReason::Symlink,
+    };
+    assert_eq!(unsafe_path(err), expected);
+    assert_eq!(fs::read(&victim).unwrap(), b"safe");
+    assert!(!tmp.join("dest").join("x").exists());
+}
+
+#[test]
+fn dir_metadata_below_replaced_dir() {
+    let tmp = temp_dir("dir_metadata_below_replaced_dir");
+    let victim = tmp.join("outside").join("b");
+    fs::create_dir(&victim).unwrap();
+    fs::set_permissions(&victim, fs::Permissions::from_mode(0o700)).unwrap();
+    let buf = archive(&[(metadata(0o40777, 0, 2), "a/b", b""), (file(), "c", b"")]);
+    extract_modified(Extractor::new(tmp.join("dest")), &buf, 1, || {
+        replace_by_symlink(&tmp.join("dest").join("a"), &tmp.join("outside"))
+    })
+    .unwrap();
+    let mode = fs::metadata(&victim).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o700);
+    let moved = fs::metadata(tmp.join("dest").join("a.moved").join("b")).unwrap();
+    assert_ne!(moved.permissions().mode() & 0o7777, 0o777);
+}
+
+#[test]
+fn keep_existing_on_failure() {
+    let tmp = temp_dir("keep_existing_on_failure");
+    let path = tmp.join("dest").join("f");
+    fs::write(&path, b"existing").unwrap();
+    // A symbolic link without a target is invalid.
+    let buf = archive(&[(symlink(), "f", b"")]);
+    let extractor = Extractor::new(tmp.join("dest")).overwrite(Overwrite::Always);
+    extract(extractor, &buf).unwrap_err();
+    assert_eq!(fs::read(&path).unwrap(), b"existing");
+    assert_eq!(list(&tmp.join("dest")), ["f"]);
+}
+
+#[test]
+fn replace_other_file_type() {
+    let tmp = temp_dir("replace_other_file_type");
+    let dest = tmp.join("dest");
+    fs::create_dir(dest.join("dir")).unwrap();
+    fs::create_dir_all(dest.join("full/x")).unwrap();
+    fs::write(dest.join("file"), b"existing").unwrap();
+    fs::write(dest.join("data"), b"existing").unwrap();
+    let dir = metadata(0o40750, 0, 2);
+    let extractor = || Extractor::new(&dest).overwrite(Overwrite::Always);
+
+    let buf = archive(&[
+        (file(), "dir", b"now a file"),
+        (dir, "file", b""),
+        (symlink(), "data", b"file"),
+    ]);
+    extract(extractor(), &buf).unwrap();
+    assert_eq!(fs::read(dest.join("dir")).unwrap(), b"now a file");
+    let dir = fs::symlink_metadata(dest.join("file")).unwrap();
+    assert!(dir.is_dir());
+    assert_eq!(dir.permissions().mode() & 0o7777, 0o750);
+    let data = fs::symlink_metadata(dest.join("data")).unwrap();
+    assert!(data.file_type().is_symlink());
+
+    // Directories which are not empty are kept.
+    let buf = archive(&[(file(), "full", b"")]);
+    let err = extract(extractor(), &buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::DirectoryNotEmpty);
+    assert!(dest.join("full/x").is_dir());
+    assert_eq!(list(&dest), ["data", "dir", "file", "full"]);
+}
+
+#[test]
+fn allow_unsafe_paths() {
+    let tmp = temp_dir("allow_unsafe_paths");
+    let buf = archive(&[(file(), "../outside/x", b"trusted")]);
+    extract(
+        Extractor::new(tmp.join("dest")).allow_unsafe_paths(true),
+        &buf,
+    )
+    .unwrap();
+    assert_eq!(fs::read(tmp.join("outside").join("x")).unwrap(), b"trusted");
+}
+
+#[test]
+fn extract_objects() {
+    let tmp = temp_dir("extract_objects");
+    let dir = Metadata {
+        mode: 0o40750,
+        nlink: 2,
+        mtime: 1_000_000,
+        ..Default::default()
+    };
+    let script = Metadata {
+        mode: 0o100755,
+        nlink: 1,
+        mtime: 2_000_000,
+        ..Default::default()
+    };
+    let buf = archive(&[
+        (dir, "bin", b""),
+        (script, "bin/init", b"#!/bin/sh\n"),
+        (symlink(), "init", b"bin/init"),
+        (metadata(0o10644, 0, 1), "fifo", b""),
+    ]);
+    extract(Extractor::new(tmp.join("dest")), &buf).unwrap();
+
+    let bin = fs::metadata(tmp.join("dest").join("bin")).unwrap();
+    assert!(bin.is_dir());
+    assert_eq!(bin.permissions().mode() & 0o7777, 0o750);
+    assert_eq!(bin.mtime(), 1_000_000);
+    let init = fs::metadata(tmp.join("dest").join("bin/init")).unwrap();
+    assert_eq!(init.permissions().mode() & 0o7777, 0o755);
+    assert_eq!(init.mtime(), 2_000_000);
+    assert_eq!(
+        fs::read(tmp.join("dest").join("init")).unwrap(),
+        b"#!/bin/sh\n"
+    );
+    let target = fs::read_link(tmp.join("dest").join("init")).unwrap();
+    assert_eq!(target, Path::new("bin/init"));
+    let fifo = fs::symlink_metadata(tmp.join("dest").join("fifo")).unwrap();
+    assert!(fifo.file_type().is_fifo());
+}
+
+#[test]
+fn hard_links() {
+    let tmp = temp_dir("hard_links");
+    // GNU cpio stores the data on the last link, other archivers on every
+    // link.
+    let buf = archive(&[
+        (metadata(0o100644, 7, 3), "a", b""),
+        (metadata(0o100644, 7, 3), "b", b""),
+        (metadata(0o100644, 7, 3), "c", b"data"),
+        (metadata(0o100644, 8, 2), "d", b"more"),
+        (metadata(0o100644, 8, 2), "e", b"more"),
+    ]);
+    extract(Extractor::new(tmp.join("dest")), &buf).unwrap();
+    let ino = |name| fs::metadata(tmp.join("dest").join(name)).unwrap().ino();
+    for name in ["a", "b", "c"] {
+        assert_eq!(fs::read(tmp.join("dest").join(name)).unwrap(), b"data");
+        assert_eq!(ino(name), ino("a"));
+    }
+    assert_eq!(fs::read(tmp.join("dest").join("e")).unwrap(), b"more");
+    assert_eq!(ino("d"), ino("e"));
+    assert_eq!(fs::metadata(tmp.join("dest").join("a")).unwrap().nlink(), 3);
+}
+
+#[test]
+fn hard_link_of_other_type() {
+    let tmp = temp_dir("hard_link_of_other_type");
+    let buf = archive(&[
+        (metadata(0o100644, 7, 2), "file", b"data"),
+        (metadata(0o10644, 7, 2), "fifo", b""),
+    ]);
+    let err = extract(Extractor::new(tmp.join("dest")), &buf).unwrap_err();
+    let expected = UnsafePathError {
+        name: "fifo".into(),
+        reason: UnsafePathReason::HardLink,
+    };
+    assert_eq!(unsafe_path(err), expected);
+    assert_eq!(fs::read(tmp.join("dest").join("file")).unwrap(), b"data");
+    assert!(!tmp.join("dest").join("fifo").exists());
+}
+
+#[test]
+fn overwrite() {
+    let old = Metadata {
+        mtime: 1_000_000,
+        ..file()
+    };
+    let buf = archive(&[(old, "f", b"archived")]);
+    let cases = [
+        (Overwrite::Never, 2_000_000, "existing"),
+        (Overwrite::IfNewer, 2_000_000, "existing"),
+        (Overwrite::IfNewer, 0, "archived"),
+        (Overwrite::Always, 2_000_000, "archived"),
+    ];
+    for (overwrite, mtime, expected) in cases {
+        let tmp = temp_dir("overwrite");
+        let path = tmp.join("dest").join("f");
+        fs::write(&path, "existing").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime))
+            .unwrap();
+        extract(Extractor::new(tmp.join("dest")).overwrite(overwrite), &buf).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            expected,
+            "{:?}",
+            overwrite
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use cpio::{CpioIndex, CpioNewcWriter, Metadata};
+
+const FILE: Metadata = Metadata {
+    ino: 1,
+    mode: 0o100644,
+    uid: 0,
+    gid: 0,
+    nlink: 1,
+    mtime: 0,
+    file_size: 0,
+    dev_major: 0,
+    dev_minor: 0,
+    rdev_major: 0,
+    rdev_minor: 0,
+    check: 0,
+};
+
+/// Writes a newc archive holding files with the given names and data.
+fn newc(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut buf = vec![0; 4096];
+    let mut writer = CpioNewcWriter::new(&mut buf);
+    for (name, data) in files {
+        writer.write(&FILE, name, data).unwrap();
+    }
+    let len = writer.finish().unwrap();
+    buf.truncate(len);
+    buf
+}
+
+/// Names which are not in normal form, and the data stored under them.
+const FILES: &[(&str, &[u8])] = &[
+    ("etc//passwd", b"passwd"),
+    ("./usr/./bin/sh", b"sh"),
+    ("/dev/.", b"dev"),
+    ("a/b", b"b"),
+    ("a-b", b"a-b"),
+];
+
+/// Lookups of the files, in every spelling, and the expected data.
+const LOOKUPS: &[(&str, Option<&[u8]>)] = &[
+    ("etc/passwd", Some(b"passwd")),
+    ("/etc//./passwd/", Some(b"passwd")),
+    ("usr/bin/sh", Some(b"sh")),
+    ("usr/./bin//sh", Some(b"sh")),
+    ("dev", Some(b"dev")),
+    ("a/b", Some(b"b")),
+    ("a//b", Some(b"b")),
+    ("a-b", Some(b"a-b")),
+    ("etc", None),
+    ("usr/bin", None),
+    ("etc/passwd/x", None),
+];
+
+#[test]
+fn index_normalizes_components() {
+    let buf = newc(FILES);
+    let mut offsets = [0; 8];
+    let index = CpioIndex::new(&buf, &mut offsets).unwrap();
+    assert_eq!(index.len(), FILES.len());
+    for &(path, data) in LOOKUPS {
+        assert_eq!(index.get(path).map(|obj| obj.data), data, "{}", path);
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn map_index_normalizes_components() {
+    let buf = newc(FILES);
+    let index = cpio::CpioMapIndex::new(&buf).unwrap();
+    assert_eq!(index.len(), FILES.len());
+    for &(path, data) in LOOKUPS {
+        assert_eq!(index.get(path).map(|obj| obj.data), data, "{}", path);
+    }
+}
+
+#[test]
+fn last_spelling_wins() {
+    let buf = newc(&[
+        ("etc//passwd", b"first"),
+        ("./etc/passwd", b"second"),
+        ("etc/./passwd", b"last"),
+    ]);
+    let mut offsets = [0; 8];
+    let index = CpioIndex::new(&buf, &mut offsets).unwrap();
+    assert_eq!(index.get("etc/passwd").unwrap().data, b"last");
+    assert_eq!(index.get("etc/passwd").unwrap().name, "etc/./passwd");
+
+    #[cfg(feature = "alloc")]
+    {
+        let index = cpio::CpioMapIndex::new(&buf).unwrap();
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.get("etc/passwd").unwrap().data, b"last");
+    }
+}
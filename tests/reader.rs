@@ -0,0 +1,333 @@
+use cpio::{
+    CpioBinReader, CpioNewcReader, CpioNewcWriter, CpioOdcReader, CpioReader, Format, Metadata,
+    ReadError,
+};
+
+const FILE: Metadata = Metadata {
+    ino: 1,
+    mode: 0o100644,
+    uid: 0,
+    gid: 0,
+    nlink: 1,
+    mtime: 1_600_000_000,
+    file_size: 0,
+    dev_major: 0,
+    dev_minor: 0,
+    rdev_major: 0,
+    rdev_minor: 0,
+    check: 0,
+};
+
+/// Writes a newc archive holding files with the given names and data.
+fn newc(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut buf = vec![0; 4096];
+    let mut writer = CpioNewcWriter::new(&mut buf);
+    for (name, data) in files {
+        writer.write(&FILE, name, data).unwrap();
+    }
+    let len = writer.finish().unwrap();
+    buf.truncate(len);
+    buf
+}
+
+fn odc_object(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut buf = format!(
+        "070707{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:011o}{:06o}{:011o}",
+        0,
+        FILE.ino,
+        FILE.mode,
+        0,
+        0,
+        1,
+        0,
+        0,
+        name.len() + 1,
+        data.len()
+    )
+    .into_bytes();
+    buf.extend_from_slice(name.as_bytes());
+    buf.push(0);
+    buf.extend_from_slice(data);
+    buf
+}
+
+/// Writes an odc archive holding files with the given names and data.
+fn odc(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for (name, data) in files {
+        buf.extend(odc_object(name, data));
+    }
+    buf.extend(odc_object("TRAILER!!!", &[]));
+    buf
+}
+
+fn bin_object(name: &str, data: &[u8], big_endian: bool) -> Vec<u8> {
+    let fields = [
+        0o070707,
+        0,
+        FILE.ino as u16,
+        FILE.mode as u16,
+        0,
+        0,
+        1,
+        0,
+        (FILE.mtime >> 16) as u16,
+        FILE.mtime as u16,
+        name.len() as u16 + 1,
+        (data.len() >> 16) as u16,
+        data.len() as u16,
+    ];
+    let mut buf: Vec<u8> = fields
+        .iter()
+        .flat_map(|&f: &u16| {
+            if big_endian {
+                f.to_be_bytes()
+            } else {
+                f.to_le_bytes()
+            }
+        })
+        .collect();
+    buf.extend_from_slice(name.as_bytes());
+    buf.push(0);
+    buf.resize(buf.len() + (name.len() + 1) % 2, 0);
+    buf.extend_from_slice(data);
+    buf.resize(buf.len() + data.len() % 2, 0);
+    buf
+}
+
+/// Writes a binary archive in the given byte order holding files with the
+/// given names and data.
+fn bin(files: &[(&str, &[u8])], big_endian: bool) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for (name, data) in files {
+        buf.extend(bin_object(name, data, big_endian));
+    }
+    buf.extend(bin_object("TRAILER!!!", &[], big_endian));
+    buf
+}
+
+/// Concatenates the archives, separated by NUL padding, and appends
+/// padding.
+fn concat(archives: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for archive in archives {
+        buf.extend_from_slice(archive);
+        buf.extend_from_slice(&[0; 512]);
+    }
+    buf
+}
+
+#[test]
+fn missing_trailer() {
+    let archive = newc(&[("a", b"data")]);
+    let truncated = &archive[..archive.len() - 124];
+    let mut reader = CpioNewcReader::new(truncated);
+    assert_eq!(reader.next().unwrap().unwrap().name, "a");
+    assert_eq!(
+        reader.next().unwrap().unwrap_err(),
+        ReadError::MissingTrailer
+    );
+    assert!(reader.next().is_none());
+    assert_eq!(reader.consumed(), truncated.len());
+
+    let archive = odc(&[("a", b"data")]);
+    let truncated = &archive[..archive.len() - odc_object("TRAILER!!!", &[]).len()];
+    let mut reader = CpioOdcReader::new(truncated);
+    assert_eq!(reader.next().unwrap().unwrap().name, "a");
+    assert_eq!(
+        reader.next().unwrap().unwrap_err(),
+        ReadError::MissingTrailer
+    );
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn missing_trailer_concatenated() {
+    let second = newc(&[("b", b"data")]);
+    let mut buf = concat(&[newc(&[("a", b"data")])]);
+    buf.extend_from_slice(&second[..second.len() - 124]);
+    let mut reader = CpioReader::new(&buf).unwrap().concatenated(true);
+    assert_eq!(reader.next().unwrap().unwrap().name, "a");
+    assert_eq!(reader.next().unwrap().unwrap().name, "b");
+    assert_eq!(reader.segment(), 1);
+    assert_eq!(
+        reader.next().unwrap().unwrap_err(),
+        ReadError::MissingTrailer
+    );
+    assert!(reader.next().is_none());
+
+    // Trailing padding after a complete archive is not an error.
+    let buf = concat(&[newc(&[("a", b"data")])]);
+    let mut reader = CpioReader::new(&buf).unwrap().concatenated(true);
+    assert_eq!(reader.next().unwrap().unwrap().name, "a");
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn truncated_concatenated() {
+    let mut buf = concat(&[newc(&[("a", b"data")])]);
+    buf.extend_from_slice(&newc(&[("b", b"data")])[..112]);
+    let mut reader = CpioReader::new(&buf).unwrap().concatenated(true);
+    assert_eq!(reader.next().unwrap().unwrap().name, "a");
+    assert_eq!(reader.next().unwrap().unwrap_err(), ReadError::BufTooShort);
+    assert_eq!(reader.remaining().len(), 112);
+}
+
+#[test]
+fn remaining_after_trailer() {
+    let archives = [
+        newc(&[("a", b"data")]),
+        odc(&[("a", b"data")]),
+        bin(&[("a", b"data")], false),
+        bin(&[("a", b"data")], true),
+    ];
+    for archive in &archives {
+        let mut buf = archive.clone();
+        buf.extend_from_slice(b"\x1f\x8bcompressed");
+
+        let mut reader = CpioReader::new(&buf).unwrap();
+        assert_eq!(reader.by_ref().count(), 1);
+        assert_eq!(reader.remaining(), b"\x1f\x8bcompressed");
+        assert_eq!(reader.consumed(), archive.len());
+    }
+
+    let buf = [odc(&[]), b"rest".to_vec()].concat();
+    let mut reader = CpioOdcReader::new(&buf);
+    assert!(reader.next().is_none());
+    assert_eq!(reader.remaining(), b"rest");
+
+    let buf = [bin(&[], false), b"rest".to_vec()].concat();
+    let mut reader = CpioBinReader::new(&buf);
+    assert!(reader.next().is_none());
+    assert_eq!(reader.remaining(), b"rest");
+}
+
+#[test]
+fn remaining_keeps_invalid_object() {
+    let mut buf = newc(&[("a", b"data")]);
+    let len = buf.len() - 124;
+    buf[len] = b'x';
+    let mut reader = CpioNewcReader::new(&buf);
+    assert_eq!(reader.next().unwrap().unwrap().name, "a");
+    assert_eq!(reader.next().unwrap().unwrap_err(), ReadError::InvalidMagic);
+    assert_eq!(reader.consumed(), len);
+    assert_eq!(reader.remaining(), &buf[len..]);
+}
+
+#[test]
+fn concatenated_segments() {
+    let buf = concat(&[newc(&[("a", b"1"), ("b", b"2")]), newc(&[("c", b"3")])]);
+    let mut reader = CpioNewcReader::new(&buf).concatenated(true);
+    let mut names = Vec::new();
+    while let Some(obj) = reader.next() {
+        names.push((obj.unwrap().name, reader.segment()));
+    }
+    assert_eq!(names, [("a", 0), ("b", 0), ("c", 1)]);
+    assert!(reader.remaining().is_empty());
+    // The final trailer does not start another archive.
+    assert_eq!(reader.segment(), 1);
+
+    // Empty archives are counted too.
+    let buf = concat(&[newc(&[("a", b"1")]), newc(&[]), newc(&[("b", b"2")])]);
+    let mut reader = CpioReader::new(&buf).unwrap().concatenated(true);
+    assert_eq!(reader.segment(), 0);
+    let mut names = Vec::new();
+    while let Some(obj) = reader.next() {
+        names.push((obj.unwrap().name, reader.segment()));
+    }
+    assert_eq!(names, [("a", 0), ("b", 2)]);
+    assert_eq!(reader.segment(), 2);
+
+    let buf = concat(&[odc(&[("a", b"1")]), odc(&[("b", b"2")])]);
+    let mut reader = CpioOdcReader::new(&buf).concatenated(true);
+    let mut names = Vec::new();
+    while let Some(obj) = reader.next() {
+        names.push((obj.unwrap().name, reader.segment()));
+    }
+    assert_eq!(names, [("a", 0), ("b", 1)]);
+
+    let buf = concat(&[bin(&[("a", b"1")], false), bin(&[("b", b"2")], true)]);
+    let mut reader = CpioBinReader::new(&buf).concatenated(true);
+    let mut names = Vec::new();
+    while let Some(obj) = reader.next() {
+        names.push((obj.unwrap().name, reader.segment()));
+    }
+    assert_eq!(names, [("a", 0), ("b", 1)]);
+}
+
+#[test]
+fn not_concatenated_stops_at_trailer() {
+    let first = newc(&[("a", b"1")]);
+    let buf = concat(&[first.clone(), newc(&[("b", b"2")])]);
+    let mut reader = CpioNewcReader::new(&buf);
+    assert_eq!(reader.next().unwrap().unwrap().name, "a");
+    assert!(reader.next().is_none());
+    assert_eq!(reader.segment(), 0);
+    assert_eq!(reader.remaining(), &buf[first.len()..]);
+}
+
+#[test]
+fn crc_checksum() {
+    let data = b"Hello, world!\n";
+    let mut buf = vec![0; 512];
+    let mut writer = CpioNewcWriter::new_crc(&mut buf);
+    writer.write(&FILE, "a", data).unwrap();
+    let len = writer.finish().unwrap();
+    buf.truncate(len);
+
+    let sum = data.iter().map(|&b| u32::from(b)).sum::<u32>();
+    let obj = CpioNewcReader::new(&buf).next().unwrap().unwrap();
+    assert_eq!(obj.metadata.check, sum);
+    assert_eq!(obj.data, data);
+
+    // Corrupt the first data byte, after the header and the padded name.
+    buf[112] ^= 1;
+    assert_eq!(&buf[110..112], b"a\0");
+    let mut reader = CpioNewcReader::new(&buf).verify_checksum(true);
+    assert_eq!(
+        reader.next().unwrap().unwrap_err(),
+        ReadError::ChecksumMismatch
+    );
+    let mut reader = CpioReader::new(&buf).unwrap().verify_checksum(true);
+    assert_eq!(
+        reader.next().unwrap().unwrap_err(),
+        ReadError::ChecksumMismatch
+    );
+
+    let obj = CpioNewcReader::new(&buf).next().unwrap().unwrap();
+    assert_eq!(obj.metadata.check, sum);
+    assert_ne!(obj.data, data);
+    let mut reader = CpioReader::new(&buf).unwrap().verify_checksum(false);
+    assert_ne!(reader.next().unwrap().unwrap().data, data);
+}
+
+#[test]
+fn bin_byte_order() {
+    let large = vec![0x5a; 0x1_0001];
+    for &big_endian in &[false, true] {
+        let buf = bin(&[("a", b"data"), ("large", &large)], big_endian);
+        let format = if big_endian {
+            Format::BinBe
+        } else {
+            Format::BinLe
+        };
+        assert_eq!(Format::detect(&buf), Some(format));
+
+        let mut reader = CpioReader::new(&buf).unwrap();
+        assert_eq!(reader.format(), format);
+        let obj = reader.next().unwrap().unwrap();
+        assert_eq!(obj.name, "a");
+        assert_eq!(obj.data, b"data");
+        assert_eq!(obj.metadata.mode, FILE.mode);
+        assert_eq!(obj.metadata.mtime, FILE.mtime);
+        let obj = reader.next().unwrap().unwrap();
+        assert_eq!(obj.metadata.file_size, 0x1_0001);
+        assert_eq!(obj.data, &large[..]);
+        assert!(reader.next().is_none());
+
+        let names: Vec<_> = CpioBinReader::new(&buf)
+            .map(|obj| obj.unwrap().name)
+            .collect();
+        assert_eq!(names, ["a", "large"]);
+    }
+}
@@ -0,0 +1,123 @@
+#![cfg(feature = "std")]
+
+use std::io::{self, Read};
+
+use cpio::{CpioStreamReader, CpioStreamWriter, Metadata, ReadError};
+
+const FILE: Metadata = Metadata {
+    ino: 1,
+    mode: 0o100644,
+    uid: 0,
+    gid: 0,
+    nlink: 1,
+    mtime: 0,
+    file_size: 0,
+    dev_major: 0,
+    dev_minor: 0,
+    rdev_major: 0,
+    rdev_minor: 0,
+    check: 0,
+};
+
+/// Writes a newc archive holding files with the given names and data.
+fn newc(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = CpioStreamWriter::new(Vec::new());
+    for (name, data) in files {
+        writer.write(&FILE, name, data).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+/// A stream which only implements [`Read`], returning at most one byte per
+/// call.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.0.len()).min(1);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+/// Returns the `ReadError` wrapped in an I/O error.
+fn read_error(err: io::Error) -> ReadError {
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    *err.into_inner().unwrap().downcast().unwrap()
+}
+
+#[test]
+fn partial_reads() {
+    let buf = newc(&[("a", b"0123456789"), ("b", b"abc"), ("c", b"xyz")]);
+    let mut reader = CpioStreamReader::new(Trickle(&buf));
+
+    let mut obj = reader.next_object().unwrap().unwrap();
+    assert_eq!(obj.name, "a");
+    let mut data = [0; 4];
+    obj.read_exact(&mut data).unwrap();
+    assert_eq!(&data, b"0123");
+
+    // The rest of `a` and all of `b` are skipped.
+    assert_eq!(reader.next_object().unwrap().unwrap().name, "b");
+    let mut obj = reader.next_object().unwrap().unwrap();
+    assert_eq!(obj.name, "c");
+    let mut data = Vec::new();
+    obj.read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"xyz");
+    assert!(reader.next_object().unwrap().is_none());
+    assert!(reader.into_inner().0.is_empty());
+}
+
+#[test]
+fn truncated_data() {
+    let buf = newc(&[("a", b"0123456789")]);
+    let mut reader = CpioStreamReader::new(Trickle(&buf[..116]));
+    let mut obj = reader.next_object().unwrap().unwrap();
+    let mut data = Vec::new();
+    let err = obj.read_to_end(&mut data).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(data, b"0123");
+}
+
+#[test]
+fn name_size_limit() {
+    let longest = "a".repeat(4095);
+    let buf = newc(&[(&longest, b"data")]);
+    let mut reader = CpioStreamReader::new(&buf[..]);
+    assert_eq!(reader.next_object().unwrap().unwrap().name, longest);
+
+    let buf = newc(&[(&"a".repeat(4096), b"data")]);
+    let mut reader = CpioStreamReader::new(&buf[..]);
+    let err = reader.next_object().err().unwrap();
+    assert_eq!(read_error(err), ReadError::InvalidName);
+
+    // The name is rejected before reading it, however large it claims to be.
+    let mut header = newc(&[("a", b"")]);
+    header[94..102].copy_from_slice(b"ffffffff");
+    let mut reader = CpioStreamReader::new(&header[..110]);
+    let err = reader.next_object().err().unwrap();
+    assert_eq!(read_error(err), ReadError::InvalidName);
+}
+
+#[test]
+fn concatenated_segments() {
+    let mut buf = newc(&[("a", b"1"), ("b", b"2")]);
+    buf.extend_from_slice(&[0; 512]);
+    buf.extend(newc(&[]));
+    buf.extend(newc(&[("c", b"3")]));
+    buf.extend_from_slice(&[0; 512]);
+
+    let mut reader = CpioStreamReader::new(Trickle(&buf)).concatenated(true);
+    assert_eq!(reader.segment(), 0);
+    let mut names = Vec::new();
+    while let Some(obj) = reader.next_object().unwrap() {
+        names.push((obj.name, reader.segment()));
+    }
+    assert_eq!(names, [("a".into(), 0), ("b".into(), 0), ("c".into(), 2)]);
+    assert_eq!(reader.segment(), 2);
+
+    let mut reader = CpioStreamReader::new(&buf[..]);
+    while reader.next_object().unwrap().is_some() {}
+    assert_eq!(reader.segment(), 0);
+}
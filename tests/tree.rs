@@ -0,0 +1,65 @@
+#![cfg(feature = "alloc")]
+
+use cpio::{CpioNewcWriter, CpioTree, Metadata};
+
+/// Writes a newc archive holding objects with the given modes, names and
+/// data.
+fn newc(objects: &[(u32, &str, &[u8])]) -> Vec<u8> {
+    let mut buf = vec![0; 4096];
+    let mut writer = CpioNewcWriter::new(&mut buf);
+    for &(mode, name, data) in objects {
+        let metadata = Metadata {
+            mode,
+            nlink: 1,
+            ..Default::default()
+        };
+        writer.write(&metadata, name, data).unwrap();
+    }
+    let len = writer.finish().unwrap();
+    buf.truncate(len);
+    buf
+}
+
+/// Returns the names and paths of the children of `path`.
+fn read_dir<'t>(tree: &'t CpioTree<'_>, path: &str) -> Vec<(&'t str, &'t str)> {
+    tree.read_dir(path)
+        .unwrap()
+        .map(|entry| (entry.name, entry.path))
+        .collect()
+}
+
+#[test]
+fn empty_and_dot_components() {
+    let buf = newc(&[
+        (0o100644, "etc//passwd", b"root"),
+        (0o40755, "usr/./bin/", b""),
+        (0o100755, "./usr/bin//./sh", b"sh"),
+        (0o100755, "usr//bin/ls", b"ls"),
+    ]);
+    let tree = CpioTree::new(&buf).unwrap();
+
+    assert_eq!(read_dir(&tree, ""), [("etc", "etc"), ("usr", "usr")]);
+    assert_eq!(read_dir(&tree, "etc"), [("passwd", "etc/passwd")]);
+    assert_eq!(read_dir(&tree, "./usr/."), [("bin", "usr/bin")]);
+    assert_eq!(
+        read_dir(&tree, "usr//bin"),
+        [("ls", "usr/bin/ls"), ("sh", "usr/bin/sh")]
+    );
+
+    assert_eq!(tree.get("etc/passwd").unwrap().data, b"root");
+    assert_eq!(tree.get("/usr/bin/sh").unwrap().data, b"sh");
+    assert_eq!(tree.get("usr/bin/").unwrap().name, "usr/./bin/");
+    assert!(tree.get("etc").is_none());
+    assert!(tree.metadata("etc").unwrap().is_dir());
+}
+
+#[test]
+fn last_object_wins() {
+    let buf = newc(&[
+        (0o100644, "init", b"first"),
+        (0o120777, "./init", b"sbin/init"),
+    ]);
+    let tree = CpioTree::new(&buf).unwrap();
+    assert_eq!(read_dir(&tree, "/"), [("init", "init")]);
+    assert_eq!(tree.read_link("init").unwrap(), Some("sbin/init"));
+}